@@ -1,7 +1,7 @@
 
 extern crate ren;
 
-use ren::{async_std::task, WindowCommand::*};
+use ren::{async_std::{task, stream::StreamExt}, WindowCommand::*};
 
 fn main()
 {
@@ -22,16 +22,16 @@ fn main()
             Update
         ]);
 
-        loop {
-             // Await the event
-             let event = connect.event(&session).await.unwrap();
-             println!("{:?}", event);
+        // Stream the events of the session
+        let mut events = connect.events(&session).unwrap();
 
-             match event {
-                 // Terminate application
-                 ren::Event::Terminate => break,
-                 _ => ()
-             }
-         }
+        while let Some(event) = events.next().await {
+            println!("{:?}", event);
+
+            // Close application
+            if event == ren::Event::CloseRequested {
+                break;
+            }
+        }
     });
 }
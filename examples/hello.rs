@@ -26,8 +26,8 @@ fn main()
         println!("{:?}", event);
 
         match event {
-            // Terminate response
-            ren::Event::Terminate => break,
+            // Close response
+            ren::Event::CloseRequested => break,
             // Display response
             ren::Event::Display(ren::DisplayEvent::Expose(map)) => {
                 let (w, h) = map.1;
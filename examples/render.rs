@@ -28,8 +28,8 @@ fn main()
     loop {
         // Wait for an event
         match connect.wait(&token).unwrap() {
-            // Terminate response
-            ren::Event::Terminate => break,
+            // Close response
+            ren::Event::CloseRequested => break,
             ren::Event::Display(ren::DisplayEvent::Expose(map)) => {
                 let (w, h) = map.1;
                 let mut cx = Context::new();
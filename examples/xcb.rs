@@ -4,7 +4,7 @@ extern crate ren;
 use ren::{
     data::XcbData,
     stat::XcbStat::{Connection, Window, VisualType},
-    Data, Body, Message,
+    Data, Body,
     WindowCommand::{Title, Dimension, Map, Update},
 };
 
@@ -18,7 +18,7 @@ fn main()
 
     connect.requests(&token, &[
         // Request the window title
-        Title(format!("Ren - {}", file!())),
+        Title(title),
         // Request the window dimensions
         Dimension((640, 480)),
         // Map the window
@@ -39,7 +39,7 @@ fn main()
         _ => unreachable!()
     };
 
-    let mut visual = match connect.request(&token, VisualType).unwrap().take_body() {
+    let visual = match connect.request(&token, VisualType).unwrap().take_body() {
         Body::Data(Data::Xcb(XcbData::VisualType(v))) => v,
         _ => unreachable!()
     };
@@ -52,10 +52,9 @@ fn main()
         // Wait for an event
         let event = connect.wait(&token).unwrap();
 
-        match event {
-            // Terminate response
-            ren::Event::Terminate => break,
-            _ => ()
+        // Close response
+        if event == ren::Event::CloseRequested {
+            break;
         }
     }
 }
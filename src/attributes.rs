@@ -0,0 +1,120 @@
+use crate::event::EventSet;
+
+/// The attributes a window is created with, used with `Connection::begin_with`.
+/// Setting them up front avoids a window showing at the wrong size
+/// or position before the first requests are handled.
+/// # Example
+/// ```
+/// use ren::{SystemType, Body, WindowAttributes, stat::WindowStat, data::WindowData};
+///
+/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+/// let session = connect.begin_with(WindowAttributes::new()
+///     .title("Ren")
+///     .dimension((640, 480))
+///     .origin((20, 20)));
+///
+/// let data = connect.request(&session, WindowStat::Dimension).unwrap().take_body();
+/// assert_eq!(data, Body::Data(WindowData::Dimension((640, 480)).into()));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowAttributes {
+	pub(crate) title: Option<String>,
+	pub(crate) class: Option<(String, String)>,
+	pub(crate) dimension: (u32, u32),
+	pub(crate) origin: (u32, u32),
+	pub(crate) border: u16,
+	pub(crate) background: Option<u32>,
+	pub(crate) override_redirect: bool,
+	pub(crate) visual: Option<(u32, u8)>,
+	pub(crate) events: Option<EventSet>,
+	pub(crate) embed: Option<u32>
+}
+
+impl Default for WindowAttributes {
+	fn default() -> Self {
+		Self {
+			title: None,
+			class: None,
+			dimension: (1, 1),
+			origin: (0, 0),
+			border: 0,
+			background: None,
+			override_redirect: false,
+			visual: None,
+			events: None,
+			embed: None
+		}
+	}
+}
+
+impl WindowAttributes {
+	/// The default attributes, a 1x1 window at (0, 0)
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the window title
+	pub fn title<S>(mut self, title: S) -> Self
+		where S: Into<String> {
+		self.title = Some(title.into());
+		self
+	}
+
+	/// Set the instance and class names, used by the window manager
+	pub fn class<S, T>(mut self, instance: S, class: T) -> Self
+		where S: Into<String>, T: Into<String> {
+		self.class = Some((instance.into(), class.into()));
+		self
+	}
+
+	/// Set the window dimension
+	pub fn dimension(mut self, dimension: (u32, u32)) -> Self {
+		self.dimension = dimension;
+		self
+	}
+
+	/// Set the window origin
+	pub fn origin(mut self, origin: (u32, u32)) -> Self {
+		self.origin = origin;
+		self
+	}
+
+	/// Set the border width
+	pub fn border(mut self, width: u16) -> Self {
+		self.border = width;
+		self
+	}
+
+	/// Set the background pixel
+	pub fn background(mut self, pixel: u32) -> Self {
+		self.background = Some(pixel);
+		self
+	}
+
+	/// Bypass the window manager, such as for popups and tooltips
+	pub fn override_redirect(mut self, enable: bool) -> Self {
+		self.override_redirect = enable;
+		self
+	}
+
+	/// Set the visual id and depth of the window instead of those of the screen
+	pub fn visual(mut self, visual: u32, depth: u8) -> Self {
+		self.visual = Some((visual, depth));
+		self
+	}
+
+	/// Set the event categories the window subscribes to, every category
+	/// by default. Change it later with `WindowCommand::Subscribe`.
+	pub fn events(mut self, events: EventSet) -> Self {
+		self.events = Some(events);
+		self
+	}
+
+	/// Create the window inside the foreign window with this id,
+	/// following the XEmbed protocol. The embedder maps the window
+	/// and reports `EmbedEvent`s.
+	pub fn embed(mut self, parent: u32) -> Self {
+		self.embed = Some(parent);
+		self
+	}
+}
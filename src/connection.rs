@@ -1,72 +1,266 @@
 
 use crate::{
-	Token, Event, Body, Message, Status, Error, MessageQueue,
-	session::Session, context::{ConnectionError}, system::{System, SystemType}
+	Token, Event, Body, Message, Type, Status, Error, MessageQueue,
+	EventProxy, WindowAttributes, Interceptor, session::Session, context::{ConnectionError}, system::{System, SystemType}
 };
-use std::{collections::HashMap, sync::{Arc, RwLock}};
+use std::{collections::HashMap, sync::{Arc, RwLock}, time::{Duration, Instant}};
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+use crate::{EventStream, stream::Reactor};
+#[cfg(feature = "record")]
+use {std::path::Path, crate::record::{Recorder, Replay}};
 
 /// A `Connection` is used as the channel for communication with the
 /// windowing system. Communication is done via `Message`s.
 /// You can either send a request (Message) and get a response or
 /// `.wait/.poll` the system for incoming `Event`s.
-/// Using the `async-rt` feature, we can asynchronously await an `Event`
-/// using the `.event` method.
+/// Using the `async-rt` or `tokio-rt` feature, we can asynchronously await an `Event`
+/// using the `.event` method or stream them using `.events`.
 pub struct Connection {
 	system: System,
-	sessions: HashMap<Token, Arc<RwLock<Session>>>
+	sessions: HashMap<Token, Arc<RwLock<Session>>>,
+	windows: HashMap<u32, Token>,
+	handlers: HashMap<String, Handler>,
+	interceptors: Vec<Box<dyn Interceptor>>,
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	reactor: Reactor
 }
 
+/// A handler of messages with a custom `Type`
+type Handler = Box<dyn Fn(&Connection, &Token, &Message) -> Status + Send + Sync>;
+
 impl Connection {
 	/// Open a new connection for communication with the default windowing system
-	pub fn open() -> Result<Self, Option<ConnectionError>> {
+	pub fn open() -> Result<Self, ConnectionError> {
 		Self::open_with(SystemType::default())
 	}
 	
-	/// Open a new connection for communication with the windowing system
-	pub fn open_with(ty: SystemType) -> Result<Self, Option<ConnectionError>> {
+	/// Open a new connection for communication with the windowing system.
+	/// The `ConnectionError` describes why the system could not be reached.
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", err))]
+	pub fn open_with(ty: SystemType) -> Result<Self, ConnectionError> {
 		Ok(Self {
 			system: System::new(ty)?,
-			sessions: HashMap::new()
+			sessions: HashMap::new(),
+			windows: HashMap::new(),
+			handlers: HashMap::new(),
+			interceptors: Vec::new(),
+			#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+			reactor: Reactor::default()
 		})
 	}
 
+	/// Get the kind of windowing system this connection communicates with
+	pub fn system(&self) -> SystemType {
+		self.system.ty()
+	}
+
 	/// Begins a new window session and returns a `Token` as reference
 	pub fn begin(&mut self) -> Token {
+		self.begin_with(WindowAttributes::default())
+	}
+
+	/// Begins a new window session with a window created using `attributes`
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+	pub fn begin_with(&mut self, attributes: WindowAttributes) -> Token {
+		let window = self.system.create_window(&attributes, None);
+		self.insert(Session::new(window))
+	}
+
+	/// Begins a new window session with a window inside the window of
+	/// the `parent` session. The child has its own events and its origin
+	/// is relative to the parent.
+	/// # Example
+	/// ```
+	/// use ren::{SystemType, WindowAttributes, WindowCommand::*};
+	///
+	/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+	/// let main = connect.begin();
+	/// let video = connect.begin_child(&main, WindowAttributes::new()
+	///     .dimension((160, 90))
+	///     .origin((10, 10))).unwrap();
+	///
+	/// connect.request(&video, Map).unwrap();
+	/// ```
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self, attributes), err(Debug)))]
+	pub fn begin_child(&mut self, parent: &Token, attributes: WindowAttributes) -> Result<Token, Error> {
+		let window = {
+			let parent = self.sessions.get(parent).ok_or(Error::Token)?;
+			let parent = parent.try_read().map_err(|_| Error::Session)?;
+			if parent.closed() {
+				return Err(Error::Closed);
+			}
+			self.system.create_window(&attributes, Some(parent.window()))
+		};
+		Ok(self.insert(Session::new(window)))
+	}
+
+	/// Begins a new window session with the existing window `id`,
+	/// created by another client. The session can query and command the
+	/// window and receives its events, but the window is not destroyed
+	/// when the session ends. A window that already has a session can
+	/// not be adopted.
+	/// # Example
+	/// ```no_run
+	/// use ren::{stat::WindowStat, WindowCommand::*};
+	///
+	/// let mut connect = ren::Connection::open().unwrap();
+	/// let session = connect.adopt(0x1a00003).unwrap();
+	///
+	/// let dimension = connect.request(&session, WindowStat::Dimension);
+	/// connect.request(&session, Title("adopted".into()));
+	/// ```
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self), err(Debug)))]
+	pub fn adopt(&mut self, id: u32) -> Result<Token, Error> {
+		if self.windows.contains_key(&id) {
+			return Err(Error::Session);
+		}
+		let window = self.system.adopt_window(id)?;
+		Ok(self.insert(Session::new(window)))
+	}
+
+	fn insert(&mut self, session: Session) -> Token {
 		let mut token = Token::new();
 		while self.sessions.contains_key(&token) {
 			token = Token::new();
 		}
-		let window = self.system.create_window();
-		let session = Session::new(window);
+		if let Some(id) = session.id() {
+			self.windows.insert(id, token);
+		}
+		#[cfg(feature = "tracing")]
+		tracing::debug!(?token, window = ?session.id(), "session begin");
 		self.sessions.insert(token, Arc::new(RwLock::new(session)));
 		token
 	}
 
+	/// Move the window of a session into the window of the `parent` session,
+	/// or make it a top-level window with `None`. The `origin` is relative to the new parent.
+	pub fn reparent(&self, token: &Token, parent: Option<&Token>, origin: (u32, u32)) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let session = session.try_read().map_err(|_| Error::Session)?;
+		match parent {
+			None => session.reparent(None, origin)?,
+			Some(parent) => {
+				let parent = self.sessions.get(parent).ok_or(Error::Token)?;
+				let parent = parent.try_read().map_err(|_| Error::Session)?;
+				session.reparent(Some(&parent), origin)?
+			}
+		}
+		Ok(Message::empty())
+	}
+
 	/// End a current window session
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self)))]
 	pub fn end(&mut self, token: &Token) -> Status {
 		match self.sessions.remove(token) {
 			None => Err(Error::Token),
-			Some(_) => Ok(Message::empty())
+			Some(_) => {
+				self.windows.retain(|_, t| t != token);
+				Ok(Message::empty())
+			}
 		}
 	}
 
-	/// Check if the connection is active
+	/// Check if the session is active. A session is no longer active
+	/// once its window is destroyed, it delivered an `Event::Terminate`
+	/// or `Event::Disconnected`, or the connection was lost.
 	pub fn active(&self, token: &Token) -> bool {
-		// TODO
-		self.sessions.contains_key(token)
+		match self.sessions.get(token) {
+			None => false,
+			// The session is in use, so it is still open
+			Some(session) => session.try_read().map(|s| !s.closed()).unwrap_or(true)
+		}
 	}
 
-	/// Send a `Message` to the windowing system
-	pub fn send(&self, token: &Token, message: Message) -> Status {
+	/// Enable or disable checked requests for a session. Each command of a
+	/// checked session waits for the system to accept it, a rejected
+	/// command fails with `Error::Protocol`. So do stats that create
+	/// a resource, such as `XcbStat::Pixmap`.
+	/// # Example
+	/// ```
+	/// use ren::{SystemType, Error, WindowCommand::*};
+	///
+	/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	/// connect.checked(&session, true).unwrap();
+	///
+	/// assert!(matches!(connect.request(&session, Dimension((0, 0))), Err(Error::Protocol { .. })));
+	/// ```
+	pub fn checked(&self, token: &Token, checked: bool) -> Status {
 		match self.sessions.get(token) {
 			None => Err(Error::Token),
-			Some(session) => session.try_write().map_err(|_| Error::Session)?.handle(&message)
+			Some(session) => {
+				Self::write(session, |session| session.set_checked(checked))?;
+				Ok(Message::empty())
+			}
 		}
 	}
 
-	/// Send a request `Message` to the windowing system
+	/// Send a `Message` to the windowing system. A message with a
+	/// `Type::Custom` is passed to the handler registered for its type.
+	pub fn send(&self, token: &Token, message: Message) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let message = match self.intercept_message(token, message)? {
+			None => return Ok(Message::empty()),
+			Some(message) => message
+		};
+		if let Type::Custom(ty) = message.ty() {
+			return self.handler(ty)?(self, token, &message);
+		}
+		Self::write(session, |session| session.handle(&message))?
+	}
+
+	/// The handler registered for the custom type `ty`
+	fn handler(&self, ty: &str) -> Result<&Handler, Error> {
+		self.handlers.get(ty).ok_or(Error::Type)
+	}
+
+	/// Lock a session to change it. Streams that found the
+	/// session busy are woken once it is released.
+	fn write<T>(session: &RwLock<Session>, f: impl FnOnce(&mut Session) -> T) -> Result<T, Error> {
+		let mut session = session.try_write().map_err(|_| Error::Session)?;
+		let result = f(&mut session);
+		#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+		{
+			let parked = Arc::clone(session.parked());
+			drop(session);
+			parked.wake();
+		}
+		Ok(result)
+	}
+
+	/// Register a handler for messages of the custom type `ty`, sent through
+	/// `.send`, `.send_async` or a batch. The session is not in use while the
+	/// handler runs, so it can send to it. The handler replaces any previous
+	/// handler of the type.
 	/// # Example
 	/// ```
+	/// use ren::{SystemType, Message, Type, Body, stat::WindowStat};
+	///
+	/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	///
+	/// connect.register("layout", |connect, token, _| {
+	///     let dimension = connect.request(token, WindowStat::Dimension)?;
+	///     let origin = connect.request(token, WindowStat::Position)?;
+	///     Ok(Message::response(Body::Custom(format!("{:?} {:?}", dimension.body(), origin.body()))))
+	/// });
+	///
+	/// let layout = Message::new(Type::Custom("layout".into()), Body::None);
+	/// assert!(connect.send(&session, layout).is_ok());
+	/// ```
+	pub fn register<S, F>(&mut self, ty: S, handler: F)
+		where S: Into<String>, F: Fn(&Connection, &Token, &Message) -> Status + Send + Sync + 'static {
+		self.handlers.insert(ty.into(), Box::new(handler));
+	}
+
+	/// Remove the handler of the custom type `ty`, returns false if there is none
+	pub fn unregister(&mut self, ty: &str) -> bool {
+		self.handlers.remove(ty).is_some()
+	}
+
+	/// Send a request `Message` to the windowing system
+	/// # Example
+	/// ```no_run
 	/// let mut connect = ren::Connection::open().unwrap();
 	/// let session = connect.begin();
 	/// connect.request(&session, ren::WindowCommand::Map);
@@ -78,7 +272,7 @@ impl Connection {
 
 	/// Send many request `Message`s to the windowing system at once.
 	/// # Example
-	/// ```
+	/// ```no_run
 	/// use ren::WindowCommand::*;
 	///
 	/// let mut connect = ren::Connection::open().unwrap();
@@ -101,27 +295,285 @@ impl Connection {
 				.collect()
 	}
 
+	/// Send a `Stat` request without waiting for the response and return
+	/// the id of the message. The response has the same id, it is collected
+	/// once with `.reply` or delivered as `Event::Reply` by `.wait/.poll`,
+	/// whichever comes first. Stats sent together share one round trip.
+	/// A message with a `Type::Custom` is passed to its handler right away.
+	/// # Example
+	/// ```
+	/// use ren::{SystemType, Event, stat::WindowStat};
+	///
+	/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	///
+	/// let position = connect.request_async(&session, WindowStat::Position).unwrap();
+	/// let dimension = connect.request_async(&session, WindowStat::Dimension).unwrap();
+	///
+	/// let response = connect.wait_reply(&session, &dimension).unwrap();
+	/// assert_eq!(response.id(), Some(&dimension));
+	/// match connect.wait(&session) {
+	///     Ok(Event::Reply(response)) => assert_eq!(response.id(), Some(&position)),
+	///     _ => unreachable!()
+	/// }
+	/// ```
+	pub fn send_async(&self, token: &Token, message: Message) -> Result<Token, Error> {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let id = Token::new();
+		let message = self.intercept_message(token, message.with_id(Some(id)))?;
+		// A custom type is handled right away, its response is kept
+		if let Some((Type::Custom(ty), message)) = message.as_ref().map(|m| (m.ty(), m)) {
+			let response = self.handler(ty)?(self, token, message)?;
+			session.try_read().map_err(|_| Error::Session)?.respond(id, response)?;
+			return Ok(id);
+		}
+		session.try_read()
+			.map_err(|_| Error::Session)?
+			.send_async(id, message.as_ref())?;
+		Ok(id)
+	}
+
+	/// Send a request `Message` without waiting for the response, see `.send_async`
+	pub fn request_async<B>(&self, token: &Token, body: B) -> Result<Token, Error>
+		where B: Into<Body> {
+		self.send_async(token, Message::request(body))
+	}
+
+	/// Take the response to the request `id` if it arrived. Fails with
+	/// `Error::NoEvent` while it is outstanding and `Error::Token`
+	/// once it was collected. This is non-blocking.
+	pub fn reply(&self, token: &Token, id: &Token) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		session.try_read().map_err(|_| Error::Session)?.reply(id, false)
+	}
+
+	/// Wait for the response to the request `id`
+	pub fn wait_reply(&self, token: &Token, id: &Token) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		session.try_read().map_err(|_| Error::Session)?.reply(id, true)
+	}
+
+	/// Add an `Interceptor` for the messages and events of every session.
+	/// It runs after the interceptors that were added before it.
+	pub fn intercept<I>(&mut self, interceptor: I)
+		where I: Interceptor + 'static {
+		self.interceptors.push(Box::new(interceptor));
+	}
+
+	/// Pass a message through the interceptors, `None` if it was dropped
+	fn intercept_message(&self, token: &Token, message: Message) -> Result<Option<Message>, Error> {
+		let mut message = message;
+		for interceptor in &self.interceptors {
+			message = match interceptor.message(token, message)? {
+				None => return Ok(None),
+				Some(message) => message
+			};
+		}
+		Ok(Some(message))
+	}
+
+	/// Pass an event through the interceptors, `None` if it was dropped
+	pub(crate) fn intercept_event(&self, token: &Token, event: Event) -> Option<Event> {
+		self.interceptors.iter().try_fold(event, |event, i| i.event(token, event))
+	}
+
+	/// An event injected by the interceptors for a session
+	pub(crate) fn inject(&self, token: &Token) -> Option<Event> {
+		self.interceptors.iter().find_map(|i| i.inject(token))
+	}
+
 	/// Wait for an `Event`. This will block until there is a response.
 	pub fn wait(&self, token: &Token) -> Result<Event, Error> {
-		match self.sessions.get(token) {
-			None => Err(Error::Token),
-			Some(session) => session.try_read().map_err(|_| Error::Session)?.wait()
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let session = session.try_read().map_err(|_| Error::Session)?;
+		loop {
+			if let Some(event) = self.inject(token) {
+				return Ok(event);
+			}
+			if let Some(event) = self.intercept_event(token, session.wait()?) {
+				return Ok(event);
+			}
 		}
 	}
 
 	/// Poll for an `Event`. This is non-blocking.
 	pub fn poll(&self, token: &Token) -> Result<Event, Error> {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let session = session.try_read().map_err(|_| Error::Session)?;
+		loop {
+			if let Some(event) = self.inject(token) {
+				return Ok(event);
+			}
+			if let Some(event) = self.intercept_event(token, session.poll()?) {
+				return Ok(event);
+			}
+		}
+	}
+
+	/// Get an `EventProxy` to post `Event::User` events to a session from any thread
+	pub fn proxy(&self, token: &Token) -> Result<EventProxy, Error> {
 		match self.sessions.get(token) {
 			None => Err(Error::Token),
-			Some(session) => session.try_read().map_err(|_| Error::Session)?.poll()
+			Some(session) => {
+				let proxy = session.try_read().map_err(|_| Error::Session)?.proxy();
+				Ok(EventProxy::new(proxy))
+			}
+		}
+	}
+
+	/// Wait for an `Event` of any session. This will block until there is a response
+	/// or a timer of a session expires.
+	/// Returns the `Token` of the session the event belongs to.
+	/// Fails with `Error::NoEvent` if no session is active.
+	/// # Example
+	/// ```no_run
+	/// let mut connect = ren::Connection::open().unwrap();
+	/// let main = connect.begin();
+	/// let tool = connect.begin();
+	///
+	/// loop {
+	///     match connect.wait_any().unwrap() {
+	///         (token, ren::Event::CloseRequested) if token == main => break,
+	///         (token, ren::Event::CloseRequested) => { connect.end(&token); },
+	///         (token, event) => println!("{:?}: {:?}", token, event)
+	///     }
+	/// }
+	/// ```
+	pub fn wait_any(&self) -> Result<(Token, Event), Error> {
+		loop {
+			// Read first, so a notification after the poll ends the wait
+			let generation = self.system.generation();
+			match self.poll_any() {
+				Err(Error::NoEvent) => (),
+				event => return event
+			}
+			if !self.sessions.keys().any(|token| self.active(token)) {
+				return Err(Error::NoEvent);
+			}
+			if let Some((id, event)) = self.system.event(generation, self.deadline()) {
+				if let Some(event) = self.route(id, event) {
+					return Ok(event);
+				}
+			}
 		}
 	}
 
-	/// With the `async-rt` feature enabled,
+	/// The earliest timer deadline of all sessions
+	fn deadline(&self) -> Option<Instant> {
+		self.sessions.values()
+			.filter_map(|session| session.try_read().ok()?.timers().deadline())
+			.min()
+	}
+
+	/// Poll for an `Event` of any session. This is non-blocking.
+	/// Returns the `Token` of the session the event belongs to.
+	pub fn poll_any(&self) -> Result<(Token, Event), Error> {
+		for token in self.sessions.keys() {
+			if let Some(event) = self.inject(token) {
+				return Ok((*token, event));
+			}
+		}
+		while let Some((id, event)) = self.system.poll() {
+			if let Some(event) = self.route(id, event) {
+				return Ok(event);
+			}
+		}
+		for (token, session) in &self.sessions {
+			let session = session.try_read().map_err(|_| Error::Session)?;
+			while let Ok(event) = session.poll() {
+				if let Some(event) = self.intercept_event(token, event) {
+					return Ok((*token, event));
+				}
+			}
+		}
+		Err(Error::NoEvent)
+	}
+
+	/// Find the session of an event read from the system
+	fn route(&self, id: u32, event: Event) -> Option<(Token, Event)> {
+		let token = self.windows.get(&id)?;
+		if let Ok(session) = self.sessions.get(token)?.try_read() {
+			session.track(&event);
+		}
+		Some((*token, self.intercept_event(token, event)?))
+	}
+
+	/// Get every `Event` that is ready without blocking.
+	/// The events of a session are returned up to and including
+	/// the event that closed it. The interceptors inject at most
+	/// one event for each session.
+	///
+	/// Use together with `.fd` to drive the connection from an external
+	/// event loop. Call this when the descriptor is readable and after
+	/// sending requests, as waiting on a reply can buffer events.
+	pub fn drain(&self) -> Vec<(Token, Event)> {
+		let mut events = Vec::new();
+		let mut ended = Vec::new();
+		// One injected event per session, an interceptor may always have one
+		for token in self.sessions.keys() {
+			if let Some(event) = self.inject(token) {
+				if matches!(event, Event::Terminate | Event::Disconnected) {
+					ended.push(*token);
+				}
+				events.push((*token, event));
+			}
+		}
+		while let Some((id, event)) = self.system.poll() {
+			events.extend(self.route(id, event));
+		}
+		for (token, session) in &self.sessions {
+			if ended.contains(token) {
+				continue;
+			}
+			let session = match session.try_read() {
+				Err(_) => continue,
+				Ok(session) => session
+			};
+			while let Ok(event) = session.poll() {
+				let closed = matches!(event, Event::Terminate | Event::Disconnected);
+				events.extend(self.intercept_event(token, event).map(|e| (*token, e)));
+				if closed {
+					break;
+				}
+			}
+		}
+		events
+	}
+
+	/// Get the file descriptor of the connection with the windowing
+	/// system, if it has one. It becomes readable when there are
+	/// events to `.drain`. The descriptor remains owned by the connection.
+	/// # Example
+	/// ```no_run
+	/// use std::os::unix::io::RawFd;
+	///
+	/// fn readable(fd: RawFd) { /* poll, epoll, mio, ... */ }
+	///
+	/// let mut connect = ren::Connection::open().unwrap();
+	/// let session = connect.begin();
+	/// connect.request(&session, ren::WindowCommand::Map);
+	///
+	/// let fd = connect.fd().unwrap();
+	/// loop {
+	///     for (token, event) in connect.drain() {
+	///         println!("{:?}: {:?}", token, event);
+	///     }
+	///     readable(fd);
+	/// }
+	/// ```
+	#[cfg(target_family = "unix")]
+	pub fn fd(&self) -> Option<std::os::unix::io::RawFd> {
+		self.system.fd()
+	}
+
+	/// With the `async-rt` or `tokio-rt` feature enabled,
 	/// we can call `.await` from an async context
 	/// which will resolve to an `Event`.
+	/// Fails with `Error::Closed` once the session is closed
+	/// and `Error::NoEvent` when the system has no more events.
 	/// # Example
-	///```
+	///```no_run
+	/// # #[cfg(feature = "async-rt")] {
 	/// use ren::async_std::task;
 	///
 	/// task::block_on(async {
@@ -138,49 +590,203 @@ impl Connection {
 	///         println!("{:?}", event);
 	///
 	///         match event {
-	///             // Terminate application
-	///             ren::Event::Terminate => break,
+	///             // Close application
+	///             ren::Event::CloseRequested => break,
 	///             _ => ()
 	///         }
 	///     }
 	/// });
+	/// # }
 	///```
-	#[cfg(feature = "async-rt")]
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
 	pub async fn event(&self, token: &Token) -> Result<Event, Error> {
-		let session = match self.sessions.get(token) {
-			None => return Err(Error::Token),
-			Some(session) => Arc::clone(session)
-		};
+		let mut events = self.events(token)?;
+		std::future::poll_fn(|cx| events.poll_event(cx)).await
+	}
 
-		async_std::task::spawn_blocking(move || {
-			session.try_read()
-				.map_err(|_| Error::Session)?
-				.wait()
-				.map_err(|_| Error::NoEvent)
-		}).await
+	/// With the `async-rt` or `tokio-rt` feature enabled,
+	/// get a `Stream` of the `Event`s of a session
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn events(&self, token: &Token) -> Result<EventStream<'_>, Error> {
+		match self.sessions.get(token) {
+			None => Err(Error::Token),
+			Some(session) => EventStream::new(self, *token, Arc::clone(session), &self.reactor, self.system.fd())
+		}
 	}
 
-	/// Batch a sequence of messages and return a batch token
-	pub fn batch(&self, token: &Token, queue: MessageQueue) -> Result<Token, Error> {
+	/// Schedule a timer on a session that expires once after `delay`.
+	/// Returns the timer token, which is delivered as `Event::Timer`
+	/// by `.wait/.poll` when the timer expires.
+	/// # Example
+	/// ```
+	/// use std::time::Duration;
+	///
+	/// let mut connect = ren::Connection::open_with(ren::SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	///
+	/// let timer = connect.timer(&session, Duration::from_millis(10)).unwrap();
+	/// assert_eq!(connect.wait(&session), Ok(ren::Event::Timer(timer)));
+	/// ```
+	pub fn timer(&self, token: &Token, delay: Duration) -> Result<Token, Error> {
+		match self.sessions.get(token) {
+			None => Err(Error::Token),
+			Some(session) => {
+				let session = session.try_read().map_err(|_| Error::Session)?;
+				let timer = session.timers().add(delay, None);
+				Ok(timer)
+			}
+		}
+	}
+
+	/// Schedule a repeating timer on a session that expires every `period`.
+	/// Returns the timer token, which is delivered as `Event::Timer`.
+	pub fn interval(&self, token: &Token, period: Duration) -> Result<Token, Error> {
 		match self.sessions.get(token) {
 			None => Err(Error::Token),
 			Some(session) => {
-				let mut session = session.try_write().map_err(|_| Error::Session)?;
-				let mut token = Token::new();
-				while session.batch.contains_key(&token) {
-					token = Token::new();
+				let session = session.try_read().map_err(|_| Error::Session)?;
+				let timer = session.timers().add(period, Some(period));
+				Ok(timer)
+			}
+		}
+	}
+
+	/// Cancel a timer of a session
+	pub fn cancel(&self, token: &Token, timer: &Token) -> Status {
+		match self.sessions.get(token) {
+			None => Err(Error::Token),
+			Some(session) => {
+				let session = session.try_read().map_err(|_| Error::Session)?;
+				let cancelled = session.timers().cancel(timer);
+				if cancelled {
+					Ok(Message::empty())
+				} else {
+					Err(Error::Token)
 				}
-				session.batch.insert(token, queue);
-				Ok(token)
 			}
 		}
 	}
 
-	/// Dispatch the message queue using a batch token
-	pub fn dispatch(&self, token: &Token, batch: &Token) -> Status {
+	/// Record every `Event` the session delivers, with the time it was
+	/// received, to the file at `path`. The file is replaced.
+	/// # Example
+	/// ```no_run
+	/// let mut connect = ren::Connection::open().unwrap();
+	/// let session = connect.begin();
+	/// connect.record(&session, "session.events").unwrap();
+	///
+	/// while connect.wait(&session) != Ok(ren::Event::CloseRequested) {}
+	/// connect.end_record(&session).unwrap();
+	/// ```
+	#[cfg(feature = "record")]
+	pub fn record<P: AsRef<Path>>(&self, token: &Token, path: P) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let session = session.try_read().map_err(|_| Error::Session)?;
+		session.set_recorder(Some(Recorder::create(path)?));
+		Ok(Message::empty())
+	}
+
+	/// Stop recording the events of a session
+	#[cfg(feature = "record")]
+	pub fn end_record(&self, token: &Token) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		session.try_read().map_err(|_| Error::Session)?.set_recorder(None);
+		Ok(Message::empty())
+	}
+
+	/// Replay a recording through `.wait/.poll` of a session. The events
+	/// are delivered at the recorded times divided by `speed`, use `1.0`
+	/// for the original speed and `f64::INFINITY` for no delay. Other
+	/// events of the session are held back until the replay is over.
+	/// # Example
+	/// ```no_run
+	/// let mut connect = ren::Connection::open_with(ren::SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	/// connect.replay(&session, "session.events", 4.0).unwrap();
+	///
+	/// while let Ok(event) = connect.poll(&session) {
+	///     println!("{:?}", event);
+	/// }
+	/// ```
+	#[cfg(feature = "record")]
+	pub fn replay<P: AsRef<Path>>(&self, token: &Token, path: P, speed: f64) -> Status {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		let session = session.try_read().map_err(|_| Error::Session)?;
+		session.set_replay(Replay::open(path, speed)?);
+		Ok(Message::empty())
+	}
+
+	/// Batch a sequence of messages and return a batch token
+	pub fn batch(&self, token: &Token, queue: MessageQueue) -> Result<Token, Error> {
 		match self.sessions.get(token) {
 			None => Err(Error::Token),
-			Some(session) => session.try_write().map_err(|_| Error::Session)?.run(batch)
+			Some(session) => {
+				Self::write(session, |session| {
+					let mut token = Token::new();
+					while session.batch.contains_key(&token) {
+						token = Token::new();
+					}
+					session.batch.insert(token, queue);
+					token
+				})
+			}
+		}
+	}
+
+	/// Dispatch the message queue using a batch token and return the
+	/// result of each message, in order. The commands are flushed to
+	/// the system once, after the last message. A failed message does
+	/// not stop the messages after it. A queue that validates is checked
+	/// as a whole first, so an invalid message fails the dispatch and
+	/// sends nothing.
+	/// # Example
+	/// ```
+	/// use ren::{SystemType, Message, MessageQueue, stat::WindowStat, WindowCommand::*};
+	///
+	/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+	/// let session = connect.begin();
+	///
+	/// let mut queue = MessageQueue::new();
+	/// queue.validate(true);
+	/// queue.enqueue(Message::request(Dimension((320, 240))));
+	/// queue.enqueue(Message::request(Map));
+	/// queue.enqueue(Message::request(WindowStat::Mapped));
+	///
+	/// let batch = connect.batch(&session, queue).unwrap();
+	/// let responses = connect.dispatch(&session, &batch).unwrap();
+	/// assert_eq!(responses.len(), 3);
+	/// assert!(responses.iter().all(Result::is_ok));
+	/// ```
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip(self), err(Debug)))]
+	pub fn dispatch(&self, token: &Token, batch: &Token) -> Result<Vec<Status>, Error> {
+		let session = self.sessions.get(token).ok_or(Error::Token)?;
+		#[cfg(feature = "tracing")]
+		let start = Instant::now();
+		let messages = session.try_read().map_err(|_| Error::Session)?.prepare(
+			batch,
+			&|message| self.intercept_message(token, message),
+			&|ty| self.handlers.contains_key(ty)
+		)?;
+
+		let responses: Vec<Status> = messages
+			.iter()
+			.map(|message| match message {
+				// Dropped by an interceptor
+				None => Ok(Message::empty()),
+				Some(message) => match message.ty() {
+					Type::Custom(ty) => self.handler(ty)?(self, token, message),
+					_ => Self::write(session, |session| session.step(message))?
+				}
+			})
+			.collect();
+		// Flush even if the session is busy, the messages were already sent
+		session.read().unwrap_or_else(|e| e.into_inner()).window().update();
+
+		#[cfg(feature = "tracing")]
+		{
+			let failed = responses.iter().filter(|r| r.is_err()).count();
+			tracing::debug!(responses = responses.len(), failed, elapsed = ?start.elapsed(), "batch dispatched");
 		}
+		Ok(responses)
 	}
 }
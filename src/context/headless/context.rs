@@ -0,0 +1,538 @@
+use std::{
+	collections::VecDeque,
+	sync::{Arc, Weak, Mutex, MutexGuard, Condvar, atomic::{AtomicU32, Ordering}},
+	time::{Duration, Instant}
+};
+use crate::{
+	Stat, Data, WindowCommand, WindowAttributes, Event, Error, DisplayEvent,
+	event::{EventSet, display::Map}
+};
+
+/// The depth of a headless window without a visual
+const DEPTH: u8 = 32;
+
+/// The X error code for an invalid request value
+const BAD_VALUE: u8 = 2;
+/// The X error code for an invalid window
+const BAD_WINDOW: u8 = 3;
+/// The X error code for mismatched request arguments
+const BAD_MATCH: u8 = 8;
+/// The X major opcode of a get window attributes request
+const GET_WINDOW_ATTRIBUTES: u8 = 3;
+/// The X major opcode of a reparent window request
+const REPARENT_WINDOW: u8 = 7;
+/// The X major opcode of a configure window request
+const CONFIGURE_WINDOW: u8 = 12;
+
+/// The id of the next headless window
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+/// The stacking order of the windows of a connection, from the
+/// bottom. Each window is kept with the id of its parent.
+type Stack = Arc<Mutex<Vec<(u32, Option<u32>)>>>;
+
+/// Wakes a wait for an event of any window of a connection
+#[derive(Default)]
+pub struct Signal {
+	generation: Mutex<u64>,
+	ready: Condvar
+}
+
+impl Signal {
+	fn lock(&self) -> MutexGuard<'_, u64> {
+		self.generation.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Tell the waiters an event may be ready
+	pub fn notify(&self) {
+		*self.lock() += 1;
+		self.ready.notify_all();
+	}
+
+	/// The number of notifications so far
+	pub fn generation(&self) -> u64 {
+		*self.lock()
+	}
+
+	/// Wait for a notification after `generation`, until `deadline` if any
+	pub fn wait(&self, generation: u64, deadline: Option<Instant>) {
+		let mut current = self.lock();
+		while *current == generation {
+			current = match deadline {
+				None => self.ready.wait(current).unwrap_or_else(|e| e.into_inner()),
+				Some(deadline) => {
+					let timeout = match deadline.checked_duration_since(Instant::now()) {
+						None => return,
+						Some(timeout) => timeout
+					};
+					self.ready.wait_timeout(current, timeout)
+						.unwrap_or_else(|e| e.into_inner()).0
+				}
+			};
+		}
+	}
+}
+
+/// A connection to the headless system. There is no server,
+/// all window state is kept in memory.
+pub struct Connection {
+	stack: Stack,
+	signal: Arc<Signal>
+}
+
+impl Connection {
+	pub fn open() -> Result<Self, super::ConnectionError> {
+		Ok(Self {
+			stack: Stack::default(),
+			signal: Arc::default()
+		})
+	}
+
+	/// The signal notified on each event of a window of the connection
+	pub fn signal(&self) -> &Arc<Signal> {
+		&self.signal
+	}
+}
+
+struct State {
+	title: String,
+	dimension: (u32, u32),
+	origin: (u32, u32),
+	parent: Option<u32>,
+	depth: u8,
+	mapped: bool,
+	closed: bool,
+	subscription: EventSet,
+	events: VecDeque<Event>,
+	#[cfg(any(feature = "render", feature = "cairo"))]
+	surface: Option<cairo::ImageSurface>
+}
+
+impl State {
+	fn new(attributes: &WindowAttributes, parent: Option<u32>) -> Self {
+		Self {
+			title: attributes.title.clone().unwrap_or_default(),
+			dimension: attributes.dimension,
+			origin: attributes.origin,
+			parent,
+			depth: attributes.visual.map(|(_, depth)| depth).unwrap_or(DEPTH),
+			mapped: false,
+			closed: false,
+			subscription: attributes.events.unwrap_or(EventSet::all()),
+			events: VecDeque::new(),
+			#[cfg(any(feature = "render", feature = "cairo"))]
+			surface: None
+		}
+	}
+
+	/// Queue an event if the window subscribed to its category
+	fn push(&mut self, category: EventSet, event: Event) {
+		if self.subscription.contains(category) {
+			self.events.push_back(event);
+		}
+	}
+
+	fn expose(&mut self) {
+		let (w, h) = self.dimension;
+		let map = Map((0, 0), (w as u16, h as u16));
+		self.push(EventSet::EXPOSURE, DisplayEvent::Expose(map).into());
+	}
+
+	fn resize(&mut self, dimension: (u32, u32)) {
+		if self.dimension == dimension {
+			return;
+		}
+		self.dimension = dimension;
+		#[cfg(any(feature = "render", feature = "cairo"))]
+		{
+			self.surface = None;
+		}
+		let (w, h) = dimension;
+		self.push(EventSet::STRUCTURE, DisplayEvent::Resize((w as u16, h as u16)).into());
+		if self.mapped {
+			self.expose();
+		}
+	}
+
+	fn map(&mut self) {
+		if !self.mapped {
+			self.mapped = true;
+			self.expose();
+		}
+	}
+
+	#[cfg(any(feature = "render", feature = "cairo"))]
+	fn surface(&mut self) -> Option<&mut cairo::ImageSurface> {
+		if self.surface.is_none() {
+			let (w, h) = self.dimension;
+			let surface = cairo::ImageSurface::create(cairo::Format::ARgb32, w as i32, h as i32);
+			self.surface = surface.ok();
+		}
+		self.surface.as_mut()
+	}
+
+	#[cfg(any(feature = "render", feature = "cairo"))]
+	fn clear(&mut self) {
+		if let Some(surface) = &self.surface {
+			let cr = cairo::Context::new(surface);
+			cr.set_source_rgba(0.0, 0.0, 0.0, 0.0);
+			cr.set_operator(cairo::Operator::Source);
+			cr.paint();
+			surface.flush();
+		}
+	}
+
+	#[cfg(not(any(feature = "render", feature = "cairo")))]
+	fn clear(&mut self) {}
+}
+
+// SAFETY: the cairo surface is created here and never cloned out, the
+// `HeadlessSurface` handles only copy pixels in and out under the window lock
+#[cfg(any(feature = "render", feature = "cairo"))]
+unsafe impl Send for State {}
+
+struct Shared {
+	state: Mutex<State>,
+	ready: Condvar,
+	signal: Arc<Signal>
+}
+
+impl Shared {
+	fn new(state: State, signal: &Arc<Signal>) -> Self {
+		Self {
+			state: Mutex::new(state),
+			ready: Condvar::new(),
+			signal: Arc::clone(signal)
+		}
+	}
+
+	/// Wake the waits on the window and on the connection
+	fn notify(&self) {
+		self.ready.notify_all();
+		self.signal.notify();
+	}
+}
+
+/// An in-memory window. Commands update the window state and
+/// generate the events a display server would have sent.
+pub struct Window {
+	id: u32,
+	shared: Arc<Shared>,
+	stack: Stack
+}
+
+impl Window {
+	/// Create a window, inside `parent` if there is one.
+	/// A new window is stacked above its siblings.
+	pub fn create(c: &Connection, attributes: &WindowAttributes, parent: Option<&Window>) -> Self {
+		let parent = parent.map(|p| p.id).or(attributes.embed);
+		let window = Self {
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+			shared: Arc::new(Shared::new(State::new(attributes, parent), &c.signal)),
+			stack: Arc::clone(&c.stack)
+		};
+		window.stack().push((window.id, parent));
+		window
+	}
+
+	/// Wrap the window `id` of another client. There are no other
+	/// headless clients, so the window starts with the default state.
+	pub fn adopt(c: &Connection, id: u32) -> Result<Self, Error> {
+		if id == 0 {
+			return Err(Error::Protocol {
+				code: BAD_WINDOW,
+				major: GET_WINDOW_ATTRIBUTES,
+				resource: id
+			});
+		}
+		let window = Self {
+			id,
+			shared: Arc::new(Shared::new(State::new(&WindowAttributes::default(), None), &c.signal)),
+			stack: Arc::clone(&c.stack)
+		};
+		window.stack().push((id, None));
+		Ok(window)
+	}
+
+	pub fn id(&self) -> u32 {
+		self.id
+	}
+
+	/// Move the window into `parent`, or make it a top-level window
+	pub fn reparent(&self, parent: Option<&Window>, origin: (u32, u32)) -> Result<(), Error> {
+		let parent = parent.map(|p| p.id);
+		let mut state = self.state();
+		let mut stack = self.stack();
+		// A window can not move into itself or one of its descendants
+		let mut ancestor = parent;
+		while let Some(id) = ancestor {
+			if id == self.id {
+				return Err(Error::Protocol {
+					code: BAD_MATCH,
+					major: REPARENT_WINDOW,
+					resource: self.id
+				});
+			}
+			ancestor = stack.iter().find(|(i, _)| *i == id).and_then(|(_, p)| *p);
+		}
+		state.parent = parent;
+		state.origin = origin;
+		// Like a new window, it is stacked above its new siblings
+		stack.retain(|(id, _)| *id != self.id);
+		stack.push((self.id, parent));
+		Ok(())
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn stack(&self) -> MutexGuard<'_, Vec<(u32, Option<u32>)>> {
+		self.stack.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Move the window above or below all of its siblings
+	fn restack(&self, above: bool) {
+		let mut stack = self.stack();
+		if let Some(index) = stack.iter().position(|(id, _)| *id == self.id) {
+			let entry = stack.remove(index);
+			if above {
+				stack.push(entry);
+			} else {
+				stack.insert(0, entry);
+			}
+		}
+	}
+
+	/// The position of the window among its siblings, from the bottom
+	fn stacking(&self, parent: Option<u32>) -> Option<usize> {
+		self.stack()
+			.iter()
+			.filter(|(_, p)| *p == parent)
+			.position(|(id, _)| *id == self.id)
+	}
+
+	pub fn proxy(&self) -> Proxy {
+		Proxy(Arc::downgrade(&self.shared))
+	}
+}
+
+/// Posts events to a headless window from any thread
+#[derive(Clone)]
+pub struct Proxy(Weak<Shared>);
+
+impl Proxy {
+	/// Queue `event` on the window, fails if the window was dropped
+	pub fn post(&self, event: Event) -> bool {
+		let shared = match self.0.upgrade() {
+			None => return false,
+			Some(shared) => shared
+		};
+		shared.state.lock().unwrap_or_else(|e| e.into_inner()).events.push_back(event);
+		shared.notify();
+		true
+	}
+}
+
+impl super::WindowContext for Window {
+	fn event(&self) -> Option<Event>
+	{
+		// Only a proxy can post an event while we wait
+		let mut state = self.state();
+		loop {
+			if let Some(event) = state.events.pop_front() {
+				return Some(event);
+			}
+			if state.closed {
+				return None;
+			}
+			state = self.shared.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+		}
+	}
+
+	fn poll(&self) -> Option<Event>
+	{
+		self.state().events.pop_front()
+	}
+
+	fn event_timeout(&self, timeout: Duration) -> Option<Event>
+	{
+		// Only a proxy can post an event while we wait
+		let deadline = Instant::now() + timeout;
+		let mut state = self.state();
+		loop {
+			if let Some(event) = state.events.pop_front() {
+				return Some(event);
+			}
+			let timeout = deadline.checked_duration_since(Instant::now())?;
+			state = self.shared.ready.wait_timeout(state, timeout)
+				.unwrap_or_else(|e| e.into_inner()).0;
+		}
+	}
+
+	fn stat(&self, status: Stat) -> Option<Data>
+	{
+		use crate::stat::WindowStat;
+		use crate::data::WindowData;
+
+		let state = self.state();
+		match status {
+			Stat::Window(status) => {
+				Some((match status {
+					WindowStat::Position => {
+						let (x, y) = state.origin;
+						WindowData::Position((x as i32, y as i32))
+					},
+					WindowStat::Dimension => WindowData::Dimension(state.dimension),
+					WindowStat::Depth => WindowData::Depth(state.depth),
+					WindowStat::Title => WindowData::Title(state.title.clone()),
+					WindowStat::Mapped => WindowData::Mapped(state.mapped),
+					WindowStat::Parent => WindowData::Parent(state.parent),
+					WindowStat::Stacking => WindowData::Stacking(self.stacking(state.parent)?)
+				}).into())
+			},
+			#[cfg(any(feature = "render", feature = "cairo"))]
+			Stat::Headless(status) => {
+				use crate::{stat::HeadlessStat, data::HeadlessData};
+				drop(state);
+				Some((match status {
+					HeadlessStat::Surface => HeadlessData::Surface(HeadlessSurface {
+						id: self.id,
+						shared: Arc::clone(&self.shared)
+					})
+				}).into())
+			},
+			_ => None
+		}
+	}
+
+	fn window(&self, command: &WindowCommand)
+	{
+		use WindowCommand::*;
+		let mut state = self.state();
+		match command {
+			Title(name) => state.title = name.clone(),
+			Dimension(dimension) => state.resize(*dimension),
+			Origin(origin) => state.origin = *origin,
+			Map => state.map(),
+			Unmap => state.mapped = false,
+			StackAbove => self.restack(true),
+			StackBelow => self.restack(false),
+			Clear => state.clear(),
+			Update => (),
+			Close => {
+				state.closed = true;
+				state.events.push_back(Event::Terminate);
+			},
+			Subscribe(events) => state.subscription = *events
+		}
+		if !state.events.is_empty() {
+			drop(state);
+			self.shared.notify();
+		}
+	}
+
+	fn validate(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		// Reject what a display server would reject
+		if let WindowCommand::Dimension((0, _)) | WindowCommand::Dimension((_, 0)) = command {
+			return Err(Error::Protocol {
+				code: BAD_VALUE,
+				major: CONFIGURE_WINDOW,
+				resource: self.id
+			});
+		}
+		Ok(())
+	}
+
+	fn window_checked(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		self.validate(command)?;
+		self.window(command);
+		Ok(())
+	}
+
+	fn update(&self) {}
+
+	fn closed(&self) -> bool
+	{
+		self.state().closed
+	}
+}
+
+impl From<&Connection> for Window {
+	fn from(c: &Connection) -> Self {
+		Self::create(c, &WindowAttributes::default(), None)
+	}
+}
+
+impl Drop for Window {
+	fn drop(&mut self) {
+		let id = self.id;
+		self.stack().retain(|(i, _)| *i != id);
+	}
+}
+
+/// A handle to the image surface a headless window renders into.
+/// The surface stays behind the window lock, the handle copies
+/// the pixels in and out.
+#[cfg(any(feature = "render", feature = "cairo"))]
+#[derive(Clone)]
+pub struct HeadlessSurface {
+	id: u32,
+	shared: Arc<Shared>
+}
+
+#[cfg(any(feature = "render", feature = "cairo"))]
+impl HeadlessSurface {
+	/// Copy the `ARgb32` pixels of the window and their stride
+	pub fn data(&self) -> Option<(Vec<u8>, i32)> {
+		let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+		let surface = state.surface()?;
+		surface.flush();
+		let stride = surface.get_stride();
+		let data = surface.get_data().ok()?.to_vec();
+		Some((data, stride))
+	}
+
+	/// Paint the window surface onto `image`
+	pub(crate) fn copy_to(&self, image: &cairo::Surface) {
+		let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+		if let Some(surface) = state.surface() {
+			surface.flush();
+			let cr = cairo::Context::new(image);
+			cr.set_source_surface(surface, 0.0, 0.0);
+			cr.set_operator(cairo::Operator::Source);
+			cr.paint();
+		}
+		image.flush();
+	}
+
+	/// Paint `image` onto the window surface
+	pub(crate) fn copy_from(&self, image: &cairo::Surface) {
+		let mut state = self.shared.state.lock().unwrap_or_else(|e| e.into_inner());
+		if let Some(surface) = state.surface() {
+			image.flush();
+			let cr = cairo::Context::new(surface);
+			cr.set_source_surface(image, 0.0, 0.0);
+			cr.set_operator(cairo::Operator::Source);
+			cr.paint();
+			drop(cr);
+			surface.flush();
+		}
+	}
+}
+
+#[cfg(any(feature = "render", feature = "cairo"))]
+impl std::fmt::Debug for HeadlessSurface {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.debug_tuple("HeadlessSurface").field(&self.id).finish()
+	}
+}
+
+#[cfg(any(feature = "render", feature = "cairo"))]
+impl PartialEq for HeadlessSurface {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.shared, &other.shared)
+	}
+}
@@ -0,0 +1,6 @@
+mod context;
+
+use super::{ConnectionError, WindowContext};
+pub use context::{Connection, Window, Proxy, Signal};
+#[cfg(any(feature = "render", feature = "cairo"))]
+pub use context::HeadlessSurface;
@@ -0,0 +1,290 @@
+use std::{
+	collections::VecDeque, fmt,
+	sync::{Arc, Weak, Mutex, MutexGuard, Condvar},
+	time::{Duration, Instant}
+};
+use crate::{Token, Stat, Data, Body, WindowCommand, WindowAttributes, Event, Error};
+use super::{WindowContext, headless};
+
+#[derive(Default)]
+struct State {
+	commands: Vec<WindowCommand>,
+	stats: Vec<Stat>,
+	script: VecDeque<Event>,
+	updates: usize,
+	closed: bool
+}
+
+#[derive(Default)]
+struct Shared {
+	state: Mutex<State>,
+	ready: Condvar,
+	signal: Arc<headless::Signal>
+}
+
+/// A handle to a mock window session. It records every
+/// `WindowCommand` and `Stat` the session receives and
+/// feeds scripted `Event`s back through `.wait/.poll`.
+/// # Example
+/// ```
+/// use ren::{Mock, SystemType, WindowCommand::*};
+///
+/// let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+/// let session = connect.begin();
+/// let mock = Mock::session(&connect, &session).unwrap();
+///
+/// mock.script(vec![ren::Event::Terminate]);
+/// connect.request(&session, Map);
+///
+/// assert_eq!(connect.wait(&session), Ok(ren::Event::Terminate));
+/// assert_eq!(mock.commands(), vec![Map]);
+/// ```
+#[derive(Clone, Default)]
+pub struct Mock(Arc<Shared>);
+
+impl Mock {
+	/// Get the mock handle of a session from a `Connection`
+	pub fn session(connect: &crate::Connection, token: &Token) -> Option<Self> {
+		use crate::{data::MockData, stat::MockStat};
+
+		match connect.request(token, MockStat::Handle).ok()?.take_body() {
+			Body::Data(Data::Mock(MockData::Handle(mock))) => Some(mock),
+			_ => None
+		}
+	}
+
+	fn new(signal: &Arc<headless::Signal>) -> Self {
+		Mock(Arc::new(Shared {
+			signal: Arc::clone(signal),
+			..Shared::default()
+		}))
+	}
+
+	fn state(&self) -> MutexGuard<'_, State> {
+		self.0.state.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn notify(&self) {
+		self.0.ready.notify_all();
+		self.0.signal.notify();
+	}
+
+	/// Append events to the script
+	pub fn script<I>(&self, events: I)
+		where I: IntoIterator<Item = Event> {
+		self.state().script.extend(events);
+		self.notify();
+	}
+
+	/// Get every `WindowCommand` received so far
+	pub fn commands(&self) -> Vec<WindowCommand> {
+		self.state().commands.clone()
+	}
+
+	/// Get every `Stat` received so far
+	pub fn stats(&self) -> Vec<Stat> {
+		self.state().stats.clone()
+	}
+
+	/// Get the number of times the window was flushed to the system
+	pub fn updates(&self) -> usize {
+		self.state().updates
+	}
+
+	/// Close the window as if it was destroyed by the system.
+	/// An `Event::Terminate` is appended to the script.
+	pub fn close(&self) {
+		let mut state = self.state();
+		state.closed = true;
+		state.script.push_back(Event::Terminate);
+		drop(state);
+		self.notify();
+	}
+
+	/// Clear the recorded commands, stats and updates
+	pub fn clear(&self) {
+		let mut state = self.state();
+		state.commands.clear();
+		state.stats.clear();
+		state.updates = 0;
+	}
+}
+
+impl fmt::Debug for Mock {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+		write!(f, "Mock")
+	}
+}
+
+impl PartialEq for Mock {
+	fn eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.0, &other.0)
+	}
+}
+
+/// Posts events to the script of a mock window from any thread
+#[derive(Clone)]
+pub struct Proxy(Weak<Shared>);
+
+impl Proxy {
+	/// Append `event` to the script, fails if the mock was dropped
+	pub fn post(&self, event: Event) -> bool {
+		match self.0.upgrade() {
+			None => false,
+			Some(shared) => {
+				Mock(shared).script([event]);
+				true
+			}
+		}
+	}
+}
+
+/// A connection to the mock system. Mock windows
+/// keep their state in a headless connection.
+pub struct Connection(headless::Connection);
+
+impl Connection {
+	pub fn open() -> Result<Self, super::ConnectionError> {
+		Ok(Connection(headless::Connection::open()?))
+	}
+
+	/// The signal notified on each event of a mock window
+	pub fn signal(&self) -> &Arc<headless::Signal> {
+		self.0.signal()
+	}
+}
+
+/// A mock window. State queries are answered by a headless
+/// window, events only come from the script.
+pub struct Window {
+	window: headless::Window,
+	mock: Mock
+}
+
+impl WindowContext for Window {
+	fn event(&self) -> Option<Event>
+	{
+		// Wait for the script to be extended or a proxy to post
+		let mut state = self.mock.state();
+		loop {
+			if let Some(event) = state.script.pop_front() {
+				return Some(event);
+			}
+			if state.closed {
+				return None;
+			}
+			state = self.mock.0.ready.wait(state).unwrap_or_else(|e| e.into_inner());
+		}
+	}
+
+	fn poll(&self) -> Option<Event>
+	{
+		self.mock.state().script.pop_front()
+	}
+
+	fn event_timeout(&self, timeout: Duration) -> Option<Event>
+	{
+		// Only a proxy can post an event while we wait
+		let deadline = Instant::now() + timeout;
+		let mut state = self.mock.state();
+		loop {
+			if let Some(event) = state.script.pop_front() {
+				return Some(event);
+			}
+			let timeout = deadline.checked_duration_since(Instant::now())?;
+			state = self.mock.0.ready.wait_timeout(state, timeout)
+				.unwrap_or_else(|e| e.into_inner()).0;
+		}
+	}
+
+	fn stat(&self, status: Stat) -> Option<Data>
+	{
+		use crate::{stat::MockStat, data::MockData};
+
+		match status {
+			Stat::Mock(MockStat::Handle) => Some(MockData::Handle(self.mock.clone()).into()),
+			_ => {
+				self.mock.state().stats.push(status);
+				self.window.stat(status)
+			}
+		}
+	}
+
+	fn window(&self, command: &WindowCommand)
+	{
+		self.mock.state().commands.push(command.clone());
+		self.window.window(command);
+		match command {
+			WindowCommand::Close => self.mock.close(),
+			WindowCommand::Update => self.update(),
+			_ => ()
+		}
+	}
+
+	fn validate(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		self.window.validate(command)
+	}
+
+	fn window_checked(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		self.mock.state().commands.push(command.clone());
+		self.window.window_checked(command)?;
+		match command {
+			WindowCommand::Close => self.mock.close(),
+			WindowCommand::Update => self.update(),
+			_ => ()
+		}
+		Ok(())
+	}
+
+	fn update(&self)
+	{
+		self.mock.state().updates += 1;
+	}
+
+	fn closed(&self) -> bool
+	{
+		self.mock.state().closed
+	}
+}
+
+impl Window {
+	pub fn proxy(&self) -> Proxy {
+		Proxy(Arc::downgrade(&self.mock.0))
+	}
+}
+
+impl Window {
+	/// Create a window, inside `parent` if there is one
+	pub fn create(c: &Connection, attributes: &WindowAttributes, parent: Option<&Window>) -> Self {
+		let parent = parent.map(|p| &p.window);
+		Self {
+			window: headless::Window::create(&c.0, attributes, parent),
+			mock: Mock::new(c.0.signal())
+		}
+	}
+
+	/// Wrap the window `id` of another client
+	pub fn adopt(c: &Connection, id: u32) -> Result<Self, Error> {
+		Ok(Self {
+			window: headless::Window::adopt(&c.0, id)?,
+			mock: Mock::new(c.0.signal())
+		})
+	}
+
+	pub fn id(&self) -> u32 {
+		self.window.id()
+	}
+
+	/// Move the window into `parent`, or make it a top-level window
+	pub fn reparent(&self, parent: Option<&Window>, origin: (u32, u32)) -> Result<(), Error> {
+		self.window.reparent(parent.map(|p| &p.window), origin)
+	}
+}
+
+impl From<&Connection> for Window {
+	fn from(c: &Connection) -> Self {
+		Self::create(c, &WindowAttributes::default(), None)
+	}
+}
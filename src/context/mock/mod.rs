@@ -0,0 +1,4 @@
+mod context;
+
+use super::{ConnectionError, WindowContext, headless};
+pub use context::{Connection, Window, Mock, Proxy};
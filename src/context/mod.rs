@@ -1,25 +1,90 @@
-
 #[cfg(target_family = "unix")]
 pub mod xcb;
+pub mod headless;
+pub mod mock;
 
-use crate::{Stat, Data, WindowCommand, event::Event};
+use std::time::Duration;
+use crate::{Stat, Data, WindowCommand, Error, event::Event};
 
 /// A connection error with the windowing system
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
 pub enum ConnectionError {
-	
+	/// The `DISPLAY` environment variable is not set
+	NoDisplay,
+	/// The display name could not be parsed. Contains the display name
+	InvalidDisplay(String),
+	/// The display server could not be reached. Contains the display name
+	Unreachable(String),
+	/// The display server rejected the client authorization. Contains the display name
+	Authorization(String),
+	/// The display server has no screen with the requested number
+	InvalidScreen(i32),
+	/// The display server does not support a required extension
+	Extension,
+	/// The connection failed on I/O or ran out of resources. Contains the reason
+	Io(String)
+}
+
+impl From<&ConnectionError> for String {
+	fn from(e: &ConnectionError) -> Self
+	{
+		use ConnectionError::*;
+		match e {
+			NoDisplay => "the DISPLAY environment variable is not set".into(),
+			InvalidDisplay(d) => format!("unable to parse the display name '{}'", d),
+			Unreachable(d) => format!("unable to reach the display server at '{}'", d),
+			Authorization(d) => format!("the display server at '{}' rejected the authorization", d),
+			InvalidScreen(n) => format!("the display server has no screen {}", n),
+			Extension => "the display server does not support a required extension".into(),
+			Io(s) => format!("connection failed: {}", s)
+		}
+	}
 }
 
+impl std::fmt::Display for ConnectionError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error>
+	{
+		write!(f, "{}", String::from(self))
+	}
+}
+
+impl std::error::Error for ConnectionError {}
+
 pub trait WindowContext {
 
 	fn event(&self) -> Option<Event>;
 
 	fn poll(&self) -> Option<Event>;
 
+	/// Wait for an event until `timeout` has passed
+	fn event_timeout(&self, _: Duration) -> Option<Event>;
+
 	fn stat(&self, _: Stat) -> Option<Data>;
 
+	/// Get a stat and wait for the system to accept any request it made
+	fn stat_checked(&self, stat: Stat) -> Result<Option<Data>, Error> {
+		Ok(self.stat(stat))
+	}
+
 	fn window(&self, _: &WindowCommand);
 
+	/// Check a command without applying it
+	fn validate(&self, _: &WindowCommand) -> Result<(), Error> {
+		Ok(())
+	}
+
+	/// Apply a command and wait for the system to accept it
+	fn window_checked(&self, command: &WindowCommand) -> Result<(), Error> {
+		self.window(command);
+		Ok(())
+	}
+
 	fn update(&self);
 
+	/// Check if the window was destroyed or the connection lost
+	fn closed(&self) -> bool {
+		false
+	}
+
 }
@@ -1,17 +1,23 @@
 
 #![allow(dead_code)]
-#![allow(unused_must_use)]
 
 extern crate xcb;
 
-use std::sync::Arc;
+use std::{
+	ptr,
+	sync::{Arc, atomic::{AtomicBool, Ordering}},
+	time::{Duration, Instant}
+};
 use crate::{
-	Stat, Data, WindowCommand, Event, DisplayEvent, KeyEvent, MouseEvent, event,
-	event::{FocusEvent, input::MouseData}
+	Stat, Data, WindowCommand, XcbCommand, WindowAttributes, Event, Error,
+	event::EventSet, stat::WindowStat, data::WindowData
 };
+use super::{ConnectionError, xembed, demux::{Demux, Atoms}};
 
 #[derive(Clone)]
 pub struct Screen {
+	number: i32,
+	root: xcb::Window,
 	width: u16,
 	height: u16,
 	depth: u8,
@@ -19,19 +25,22 @@ pub struct Screen {
 }
 
 impl Screen {
-	fn from(screen: &xcb::Screen) -> Self {
-		let root_depth = screen.root_depth();
-		let depth = screen.allowed_depths().find(|d| d.depth() == root_depth);
-		
-		let id = screen.root_visual();
-		let visual = depth.map(|d| d.visuals())
-			.map(|mut v| v.find(|v| v.visual_id() == id))
-			.flatten();
+	fn from(screen: &xcb::Screen, number: i32) -> Self {
+		Self::with_visual(screen, number, screen.root_depth(), screen.root_visual())
+	}
+
+	/// The screen as seen by a window using `visual` at `depth`
+	fn with_visual(screen: &xcb::Screen, number: i32, depth: u8, id: xcb::Visualid) -> Self {
+		let allowed = screen.allowed_depths().find(|d| d.depth() == depth);
+		let visual = allowed.map(|d| d.visuals())
+			.and_then(|mut v| v.find(|v| v.visual_id() == id));
 		
 		Self {
+			number,
+			root: screen.root(),
 			width: screen.width_in_pixels(),
 			height: screen.height_in_pixels(),
-			depth: root_depth,
+			depth,
 			visual
 		}
 	}
@@ -41,37 +50,105 @@ pub struct Connection {
 	connection: Arc<xcb::Connection>,
 	preference: i32,
 	screen: Screen,
-	delete: Option<xcb::Atom>
+	demux: Arc<Demux>
 }
 
 impl Connection {
-	pub fn open() -> Result<Self, Option<super::ConnectionError>> {
-		let (connect, num) = match xcb::Connection::connect(None) {
-			Err(_) => return Err(None),
+	pub fn open() -> Result<Self, super::ConnectionError> {
+		let display = match std::env::var("DISPLAY") {
+			Ok(display) if !display.is_empty() => display,
+			_ => return Err(ConnectionError::NoDisplay)
+		};
+		let (connect, num) = match xcb::Connection::connect(Some(&display)) {
+			Err(e) => return Err(connection_error(&display, e)),
 			Ok((c, n)) => (c, n)
 		};
 		let setup = connect.get_setup();
 		let screen = match setup.roots().nth(num as usize) {
-			None => return Err(None),
-			Some(screen) => Screen::from(&screen)
+			None => return Err(ConnectionError::InvalidScreen(num)),
+			Some(screen) => Screen::from(&screen, num)
 		};
 
-		let cookie = xcb::intern_atom(&connect, true, "WM_PROTOCOLS");
-		cookie.get_reply();
-		let cookie = xcb::intern_atom(&connect, false, "WM_DELETE_WINDOW");
-		let delete = cookie.get_reply().ok().map(|r| r.atom());
-		
+		let atoms = Atoms::intern(&connect);
+		let connection = Arc::new(connect);
 		Ok(Self {
-			connection: Arc::new(connect),
+			demux: Arc::new(Demux::new(Arc::clone(&connection), atoms, screen.root)),
+			connection,
 			preference: num,
-			screen,
-			delete
+			screen
 		})
 	}
 	
 	pub fn flush(&self) {
 		self.connection.flush();
 	}
+
+	/// Wait for an event of any window
+	pub fn event(&self, deadline: Option<Instant>) -> Option<(xcb::Window, Event)> {
+		self.demux.wait_any(deadline)
+	}
+
+	/// Poll for an event of any window
+	pub fn poll(&self) -> Option<(xcb::Window, Event)> {
+		self.demux.poll_any()
+	}
+}
+
+/// Map a failure to connect to `display`. `xcb` reports every
+/// socket and authorization failure the same way, so the
+/// display socket is probed to tell them apart.
+fn connection_error(display: &str, error: xcb::ConnError) -> ConnectionError
+{
+	use xcb::ConnError::*;
+	match error {
+		Connection => match probe(display) {
+			None => ConnectionError::InvalidDisplay(display.into()),
+			Some(false) => ConnectionError::Unreachable(display.into()),
+			Some(true) => ConnectionError::Authorization(display.into())
+		},
+		ClosedParseErr => ConnectionError::InvalidDisplay(display.into()),
+		ClosedInvalidScreen => {
+			let screen = parse_display(display).and_then(|(_, _, s)| s);
+			ConnectionError::InvalidScreen(screen.unwrap_or(0))
+		},
+		ClosedExtNotSupported => ConnectionError::Extension,
+		ClosedMemInsufficient => ConnectionError::Io("insufficient memory".into()),
+		ClosedReqLenExceed => ConnectionError::Io("exceeded the maximum request length".into()),
+		ClosedFdPassingFailed => ConnectionError::Io("file descriptor passing failed".into())
+	}
+}
+
+/// Split a display name of the form `[host]:display[.screen]`
+fn parse_display(display: &str) -> Option<(&str, u16, Option<i32>)>
+{
+	let (host, rest) = display.rsplit_once(':')?;
+	let (number, screen) = match rest.split_once('.') {
+		None => (rest, None),
+		Some((n, s)) => (n, Some(s.parse().ok()?))
+	};
+	Some((host, number.parse().ok()?, screen))
+}
+
+/// How long to wait for a remote display server to accept the probe
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Check if the display server socket accepts connections.
+/// `None` if the display name could not be parsed.
+fn probe(display: &str) -> Option<bool>
+{
+	use std::{net::{TcpStream, ToSocketAddrs}, os::unix::net::UnixStream};
+
+	let (host, number, _) = parse_display(display)?;
+	Some(match host {
+		"" | "unix" => UnixStream::connect(format!("/tmp/.X11-unix/X{}", number)).is_ok(),
+		path if path.starts_with('/') => UnixStream::connect(path).is_ok(),
+		host => {
+			let addrs = (host, 6000u16.checked_add(number)?).to_socket_addrs();
+			addrs.into_iter()
+				.flatten()
+				.any(|addr| TcpStream::connect_timeout(&addr, PROBE_TIMEOUT).is_ok())
+		}
+	})
 }
 
 impl std::ops::Deref for Connection {
@@ -82,21 +159,49 @@ impl std::ops::Deref for Connection {
 	}
 }
 
+/// The result of a request, always `Ok` for unchecked requests
+type Request = Result<(), xcb::GenericError>;
+
+/// Send a request using the `_checked` variant of the xcb
+/// function when `checked`, waiting for the server to accept it
+macro_rules! request {
+	($checked:expr, $f:ident | $checked_f:ident($($arg:expr),*)) => {
+		if $checked {
+			xcb::$checked_f($($arg),*).request_check()
+		} else {
+			xcb::$f($($arg),*);
+			Ok(())
+		}
+	};
+}
+
 pub struct Window {
 	window: xcb::Window,
 	connection: Arc<xcb::Connection>,
 	screen: Screen,
-	delete: Option<xcb::Atom>
+	demux: Arc<Demux>,
+	destroyed: AtomicBool,
+	/// If `Event::Disconnected` was delivered
+	disconnected: AtomicBool,
+	/// If the window is embedded using XEmbed
+	embedded: bool,
+	/// If the window was created by another client
+	adopted: bool
 }
 
 impl Window {
 	fn new(window: xcb::Window, connection: Arc<xcb::Connection>,
-		screen: Screen, delete: Option<xcb::Atom>) -> Self {
+		screen: Screen, demux: Arc<Demux>, embedded: bool) -> Self {
+		demux.register(window);
 		Self {
 			window,
 			connection,
 			screen,
-			delete
+			demux,
+			destroyed: AtomicBool::new(false),
+			disconnected: AtomicBool::new(false),
+			embedded,
+			adopted: false
 		}
 	}
 
@@ -104,8 +209,9 @@ impl Window {
 		self.connection.generate_id()
 	}
 
-	fn property<T>(&self, mode: xcb::PropMode, prop: xcb::AtomEnum, ty: xcb::AtomEnum, data: &[T]) {
-		xcb::change_property(
+	fn property<T>(&self, checked: bool, mode: xcb::PropMode,
+		prop: xcb::AtomEnum, ty: xcb::AtomEnum, data: &[T]) -> Request {
+		request!(checked, change_property | change_property_checked(
 			&self.connection,
 			mode as u8,
 			self.window,
@@ -113,331 +219,704 @@ impl Window {
 			ty,
 			8,
 			data
-		);
+		))
 	}
 
-	fn configure(&self, values: &[(u16, u32)]) {
-		xcb::configure_window(
+	fn configure(&self, checked: bool, values: &[(u16, u32)]) -> Request {
+		request!(checked, configure_window | configure_window_checked(
 			&self.connection,
 			self.window,
 			values
-		);
+		))
 	}
 
 	fn geometry(&self) -> Option<xcb::GetGeometryReply> {
 		xcb::get_geometry(&self.connection, self.window).get_reply().ok()
 	}
 
-	fn stat_position(&self) -> Option<(i32, i32)> {
-		self.geometry().map(|g| (g.x() as i32, g.y() as i32))
+	fn stat_depth(&self) -> Option<u8> {
+		self.geometry().map(|g| g.depth())
 	}
 
-	fn stat_dimension(&self) -> Option<(u32, u32)> {
-		self.geometry().map(|g| (g.width() as u32, g.height() as u32))
+	/// The position of the window among the children of its parent,
+	/// which the system lists from the bottom
+	fn stat_stacking(&self) -> Option<usize> {
+		let reply = xcb::query_tree(&self.connection, self.window).get_reply().ok()?;
+		let siblings = xcb::query_tree(&self.connection, reply.parent()).get_reply().ok()?;
+		siblings.children().iter().position(|w| *w == self.window)
+	}
+
+	/// Send the request of a window stat without waiting for the reply.
+	/// `None` if the stat takes more than one request.
+	pub fn pending(&self, stat: WindowStat) -> Option<Pending> {
+		use WindowStat::*;
+		let connection = &self.connection;
+		let sequence = match stat {
+			Position | Dimension | Depth => sequence(xcb::get_geometry(connection, self.window)),
+			Title => sequence(xcb::get_property(connection, false, self.window,
+				xcb::ATOM_WM_NAME, xcb::ATOM_STRING, 0, u32::MAX)),
+			Mapped => sequence(xcb::get_window_attributes(connection, self.window)),
+			Parent => sequence(xcb::query_tree(connection, self.window)),
+			Stacking => return None
+		};
+		Some(Pending {
+			connection: Arc::clone(connection),
+			stat,
+			root: self.screen.root,
+			sequence,
+			done: false
+		})
 	}
 
-	fn stat_depth(&self) -> Option<u8> {
-		self.geometry().map(|g| g.depth())
+	/// Move the window into `parent`, or make it a top-level window
+	pub fn reparent(&self, parent: Option<&Window>, (x, y): (u32, u32)) -> Result<(), Error> {
+		let parent = parent.map(|p| p.window).unwrap_or(self.screen.root);
+		xcb::reparent_window_checked(&self.connection, self.window, parent, x as i16, y as i16)
+			.request_check()
+			.map_err(protocol_error)
 	}
 
-	fn title(&self, name: &str) {
+	fn title(&self, checked: bool, name: &str) -> Request {
 		use xcb::*;
-		self.property(PROP_MODE_REPLACE, ATOM_WM_NAME, ATOM_STRING, name.as_bytes());
+		self.property(checked, PROP_MODE_REPLACE, ATOM_WM_NAME, ATOM_STRING, name.as_bytes())
 	}
 
-	fn icon_title(&self, name: &str) {
+	fn icon_title(&self, checked: bool, name: &str) -> Request {
 		use xcb::*;
-		self.property(PROP_MODE_REPLACE, ATOM_WM_ICON_NAME, ATOM_STRING, name.as_bytes());
+		self.property(checked, PROP_MODE_REPLACE, ATOM_WM_ICON_NAME, ATOM_STRING, name.as_bytes())
 	}
 
-	fn create_pixmap(&self, width: u16, height: u16) -> Option<xcb::Pixmap> {
+	fn create_pixmap(&self, checked: bool, width: u16, height: u16) -> Result<Option<xcb::Pixmap>, xcb::GenericError> {
+		let depth = match self.stat_depth() {
+			None => return Ok(None),
+			Some(depth) => depth
+		};
 		let pixmap = self.id();
-		let depth = self.stat_depth()?;
-		xcb::create_pixmap(&self.connection, depth, pixmap, self.window, width, height);
-		Some(pixmap)
+		request!(checked, create_pixmap | create_pixmap_checked(
+			&self.connection, depth, pixmap, self.window, width, height
+		))?;
+		Ok(Some(pixmap))
 	}
 
-	fn x(&self, x: u32) {
-		self.configure(&[(
+	fn x(&self, checked: bool, x: u32) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_X as u16, x
-		)]);
+		)])
 	}
 
-	fn y(&self, y: u32) {
-		self.configure(&[(
+	fn y(&self, checked: bool, y: u32) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_Y as u16, y
-		)]);
+		)])
 	}
 
-	fn move_to(&self, x: u32, y: u32) {
-		self.configure(&[
+	fn move_to(&self, checked: bool, x: u32, y: u32) -> Request {
+		self.configure(checked, &[
 			(xcb::CONFIG_WINDOW_X as u16, x),
 			(xcb::CONFIG_WINDOW_Y as u16, y)
-		]);
+		])
 	}
 
-	fn width(&self, width: u32) {
-		self.configure(&[(
+	fn width(&self, checked: bool, width: u32) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_WIDTH as u16, width
-		)]);
+		)])
 	}
 
-	fn height(&self, height: u32) {
-		self.configure(&[(
+	fn height(&self, checked: bool, height: u32) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_HEIGHT as u16, height
-		)]);
+		)])
 	}
 
-	fn resize(&self, width: u32, height: u32) {
-		self.configure(&[
+	fn resize(&self, checked: bool, width: u32, height: u32) -> Request {
+		self.configure(checked, &[
 			(xcb::CONFIG_WINDOW_WIDTH  as u16, width),
 			(xcb::CONFIG_WINDOW_HEIGHT as u16, height)
-		]);
+		])
+	}
+
+	fn map(&self, checked: bool) -> Request {
+		// The embedder maps an embedded client
+		if self.embedder_maps() {
+			return self.embed_info(checked, xembed::MAPPED);
+		}
+		request!(checked, map_window | map_window_checked(&self.connection, self.window))
+	}
+
+	fn unmap(&self, checked: bool) -> Request {
+		if self.embedder_maps() {
+			return self.embed_info(checked, 0);
+		}
+		request!(checked, unmap_window | unmap_window_checked(&self.connection, self.window))
+	}
+
+	/// Check if the window is embedded and mapped through `_XEMBED_INFO`
+	fn embedder_maps(&self) -> bool {
+		self.embedded && self.demux.atoms().xembed_info.is_some()
+	}
+
+	/// Set the `_XEMBED_INFO` of the window, the embedder maps it
+	/// when `flags` has `xembed::MAPPED`
+	fn embed_info(&self, checked: bool, flags: u32) -> Request {
+		let info = match self.demux.atoms().xembed_info {
+			None => return Ok(()),
+			Some(info) => info
+		};
+		request!(checked, change_property | change_property_checked(
+			&self.connection,
+			xcb::PROP_MODE_REPLACE as u8,
+			self.window,
+			info,
+			info,
+			32,
+			&[xembed::VERSION, flags]
+		))
+	}
+
+	/// Embed the foreign `client` window into this window
+	fn embed(&self, checked: bool, client: xcb::Window) -> Request {
+		let atoms = self.demux.atoms();
+
+		// A client without `_XEMBED_INFO` is mapped right away
+		let info = atoms.xembed_info.and_then(|info| {
+			let cookie = xcb::get_property(&self.connection, false, client, info, info, 0, 2);
+			let reply = cookie.get_reply().ok()?;
+			match reply.value::<u32>() {
+				[_, flags] => Some(*flags),
+				_ => None
+			}
+		});
+		let flags = info.unwrap_or(xembed::MAPPED);
+
+		request!(checked, reparent_window | reparent_window_checked(
+			&self.connection, client, self.window, 0, 0
+		))?;
+
+		// Follow changes of the `MAPPED` flag
+		if atoms.xembed_info.is_some() {
+			request!(checked, change_window_attributes | change_window_attributes_checked(
+				&self.connection, client, &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)]
+			))?;
+			self.demux.embed(client, self.window);
+		}
+
+		// only version 0 of the protocol exists
+		self.xembed(checked, client, xembed::EMBEDDED_NOTIFY, 0, [self.window, xembed::VERSION])?;
+
+		if flags & xembed::MAPPED != 0 {
+			request!(checked, map_window | map_window_checked(&self.connection, client))?;
+		}
+		Ok(())
 	}
 
-	fn map(&self) {
-		xcb::map_window(&self.connection, self.window);
+	/// Send the XEmbed `message` to the embedded `client`
+	fn xembed(&self, checked: bool, client: xcb::Window, message: u32, detail: u32, data: [u32; 2]) -> Request {
+		let xembed = match self.demux.atoms().xembed {
+			None => return Ok(()),
+			Some(xembed) => xembed
+		};
+		let data = [xcb::CURRENT_TIME, message, detail, data[0], data[1]];
+		let message = xcb::ClientMessageEvent::new(32, client, xembed,
+			xcb::ClientMessageData::from_data32(data));
+		request!(checked, send_event | send_event_checked(
+			&self.connection, false, client, xcb::EVENT_MASK_NO_EVENT, &message
+		))
 	}
 
-	fn unmap(&self) {
-		xcb::unmap_window(&self.connection, self.window);
+	/// Send an XCB command
+	fn xcb(&self, checked: bool, command: &XcbCommand) -> Request {
+		match command {
+			XcbCommand::Embed(client) => self.embed(checked, *client),
+			XcbCommand::Activate(client) => {
+				self.xembed(checked, *client, xembed::WINDOW_ACTIVATE, 0, [0, 0])
+			},
+			XcbCommand::Deactivate(client) => {
+				self.xembed(checked, *client, xembed::WINDOW_DEACTIVATE, 0, [0, 0])
+			},
+			XcbCommand::FocusIn(client) => {
+				self.xembed(checked, *client, xembed::FOCUS_IN, xembed::FOCUS_CURRENT, [0, 0])
+			},
+			XcbCommand::FocusOut(client) => {
+				self.xembed(checked, *client, xembed::FOCUS_OUT, 0, [0, 0])
+			}
+		}
+	}
+
+	/// Handle an XCB command, waiting for the server to accept it when `checked`
+	pub fn xcb_command(&self, checked: bool, command: &XcbCommand) -> Result<(), Error> {
+		match self.xcb(checked, command) {
+			Ok(()) => Ok(()),
+			Err(e) => Err(protocol_error(e))
+		}
 	}
 
-	fn stack_above(&self) {
-		self.configure(&[(
+	fn stack_above(&self, checked: bool) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE
-		)]);
+		)])
 	}
 
-	fn stack_below(&self) {
-		self.configure(&[(
+	fn stack_below(&self, checked: bool) -> Request {
+		self.configure(checked, &[(
 			xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_BELOW
-		)]);
+		)])
 	}
 
-	fn window_event_map(&self, event: Option<xcb::base::GenericEvent>) -> Option<Event> {
-		if event.is_none() {
-			if let Err(_) = self.connection.has_error() {
-				return Some(Event::Terminate);
-			}
+	fn subscribe(&self, checked: bool, events: EventSet) -> Request {
+		request!(checked, change_window_attributes | change_window_attributes_checked(
+			&self.connection, self.window, &[(xcb::CW_EVENT_MASK, event_mask(events))]
+		))
+	}
+
+	/// `Event::Disconnected` once the connection is lost, it is only delivered once
+	fn disconnected(&self) -> Option<Event> {
+		match self.connection.has_error() {
+			Err(_) if !self.disconnected.swap(true, Ordering::Relaxed) => Some(Event::Disconnected),
+			_ => None
+		}
+	}
+
+	pub fn xid(&self) -> xcb::Window {
+		self.window
+	}
+
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn register_waker(&self, waker: &std::task::Waker) {
+		self.demux.register_waker(self.window, waker);
+	}
+
+	pub fn proxy(&self) -> Proxy {
+		Proxy {
+			window: self.window,
+			demux: Arc::clone(&self.demux)
 		}
+	}
+
+	fn destroy(&self, checked: bool) -> Request {
+		// Mark the window destroyed once the request is accepted or queued
+		request!(checked, destroy_window | destroy_window_checked(&self.connection, self.window))?;
+		self.destroyed.store(true, Ordering::Relaxed);
+		Ok(())
+	}
 
-		event.map(|e| {
-			let response = event_type(&e);
-
-			match response {
-				xcb::EXPOSE => {
-					DisplayEvent::Expose(event::xcb::expose(&e)).into()
-				},
-
-				xcb::KEY_PRESS => {
-					KeyEvent::Press(event::xcb::key_press(&e)).into()
-				},
-
-				xcb::KEY_RELEASE => {
-					KeyEvent::Release(event::xcb::key_release(&e)).into()
-				},
-
-				xcb::BUTTON_PRESS => {
-					let (pos, button) = event::xcb::button_press(&e);
-					MouseEvent::Press(MouseData::new(button, pos)).into()
-				},
-
-				xcb::BUTTON_RELEASE => {
-					let (pos, button) = event::xcb::button_release(&e);
-					MouseEvent::Release(MouseData::new(button, pos)).into()
-				},
-
-				xcb::MOTION_NOTIFY => {
-					MouseEvent::Move(event::xcb::mouse_move(&e)).into()
-				},
-
-				xcb::ENTER_NOTIFY => {
-					MouseEvent::Enter(event::xcb::mouse_enter(&e)).into()
-				},
-
-				xcb::LEAVE_NOTIFY => {
-					MouseEvent::Leave(event::xcb::mouse_leave(&e)).into()
-				},
-
-				xcb::FOCUS_IN => DisplayEvent::from(FocusEvent::Gain).into(),
-
-				xcb::FOCUS_OUT => DisplayEvent::from(FocusEvent::Lose).into(),
-				
-				xcb::CONFIGURE_NOTIFY => {
-					let event = unsafe { xcb::cast_event::<xcb::ConfigureNotifyEvent>(&e) };
-					DisplayEvent::Resize((event.width(), event.height())).into()
-				},
-
-				xcb::CLIENT_MESSAGE => {
-					let event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(&e) };
-					if event.type_() == 32 {
-						if let Some(del) = self.delete {
-							if del == event.data().data32()[0] {
-								return Event::Terminate;
-							}
-						}
-					}
-					Event::Unknown(Some(response.into()))
-				},
-
-				_ => Event::Unknown(Some(response.into()))
+	fn clear(&self, checked: bool) -> Request {
+		request!(checked, clear_area | clear_area_checked(&self.connection, true, self.window, 0, 0, 0, 0))
+	}
+
+	/// Send a command, a checked command waits for the server to accept it
+	fn command(&self, checked: bool, command: &WindowCommand) -> Request {
+		use WindowCommand::*;
+		match command {
+			Title(name) => self.title(checked, name),
+			Dimension((w, h)) => self.resize(checked, *w, *h),
+			Origin((x, y)) => self.move_to(checked, *x, *y),
+			Map => self.map(checked),
+			Unmap => self.unmap(checked),
+			StackAbove => self.stack_above(checked),
+			StackBelow => self.stack_below(checked),
+			Clear => self.clear(checked),
+			Close => self.destroy(checked),
+			Subscribe(events) => self.subscribe(checked, *events),
+			Update => {
+				<Self as super::WindowContext>::update(self);
+				Ok(())
 			}
-		})
+		}
 	}
+}
 
-	fn clear(&self) {
-		xcb::clear_area(&self.connection, true, self.window, 0, 0, 0, 0);
+/// Convert the error of a checked request
+fn protocol_error(e: xcb::GenericError) -> Error
+{
+	let e = unsafe { &*e.ptr };
+	Error::Protocol {
+		code: e.error_code,
+		major: e.major_code,
+		resource: e.resource_id
+	}
+}
+
+extern "C" {
+	// Not exported by the `xcb` crate
+	fn xcb_poll_for_reply(c: *mut xcb::ffi::xcb_connection_t, request: libc::c_uint,
+		reply: *mut *mut libc::c_void, error: *mut *mut xcb::ffi::xcb_generic_error_t) -> libc::c_int;
+	fn xcb_wait_for_reply(c: *mut xcb::ffi::xcb_connection_t, request: libc::c_uint,
+		error: *mut *mut xcb::ffi::xcb_generic_error_t) -> *mut libc::c_void;
+}
+
+/// The sequence number of a request. The reply is
+/// kept by the connection until it is read or discarded.
+fn sequence<T: Copy + xcb::CookieSeq>(cookie: xcb::Cookie<'_, T>) -> libc::c_uint {
+	let sequence = cookie.cookie.sequence();
+	std::mem::forget(cookie);
+	sequence
+}
+
+/// A window stat sent to the system, the reply is read later.
+/// Many stats can be sent before reading the first reply.
+pub struct Pending {
+	connection: Arc<xcb::Connection>,
+	stat: WindowStat,
+	root: xcb::Window,
+	sequence: libc::c_uint,
+	done: bool
+}
+
+impl Pending {
+	/// Take the reply if it arrived, `None` while it is outstanding
+	pub fn poll(&mut self) -> Option<Option<Data>> {
+		self.connection.flush();
+		let mut reply = ptr::null_mut();
+		let mut error = ptr::null_mut();
+		let ready = unsafe {
+			xcb_poll_for_reply(self.connection.get_raw_conn(), self.sequence, &mut reply, &mut error)
+		};
+		if ready == 0 {
+			return None;
+		}
+		Some(self.read(reply, error))
+	}
+
+	/// Wait for the reply
+	pub fn wait(&mut self) -> Option<Data> {
+		let mut error = ptr::null_mut();
+		let reply = unsafe {
+			xcb_wait_for_reply(self.connection.get_raw_conn(), self.sequence, &mut error)
+		};
+		self.read(reply, error)
+	}
+
+	fn read(&mut self, reply: *mut libc::c_void, error: *mut xcb::ffi::xcb_generic_error_t) -> Option<Data> {
+		self.done = true;
+		if !error.is_null() {
+			unsafe { libc::free(error.cast()) };
+		}
+		if reply.is_null() {
+			return None;
+		}
+
+		use WindowStat::*;
+		let data = match self.stat {
+			Position | Dimension | Depth => {
+				let g = xcb::GetGeometryReply { ptr: reply.cast() };
+				match self.stat {
+					Position => WindowData::Position((g.x() as i32, g.y() as i32)),
+					Dimension => WindowData::Dimension((g.width() as u32, g.height() as u32)),
+					_ => WindowData::Depth(g.depth())
+				}
+			},
+			Title => {
+				let reply = xcb::GetPropertyReply { ptr: reply.cast() };
+				WindowData::Title(String::from_utf8_lossy(reply.value::<u8>()).into_owned())
+			},
+			Mapped => {
+				let reply = xcb::GetWindowAttributesReply { ptr: reply.cast() };
+				WindowData::Mapped(reply.map_state() == xcb::MAP_STATE_VIEWABLE as u8)
+			},
+			Parent => {
+				let reply = xcb::QueryTreeReply { ptr: reply.cast() };
+				WindowData::Parent(Some(reply.parent()).filter(|p| *p != self.root))
+			},
+			// Never pending
+			Stacking => return None
+		};
+		Some(data.into())
+	}
+}
+
+impl Drop for Pending {
+	fn drop(&mut self) {
+		if !self.done {
+			unsafe { xcb::ffi::xcb_discard_reply(self.connection.get_raw_conn(), self.sequence) };
+		}
+	}
+}
+
+/// Posts events to a window from any thread
+#[derive(Clone)]
+pub struct Proxy {
+	window: xcb::Window,
+	demux: Arc<Demux>
+}
+
+impl Proxy {
+	/// Queue `event` on the window, fails if the window was destroyed
+	pub fn post(&self, event: Event) -> bool {
+		self.demux.post(self.window, event)
 	}
 }
 
 impl super::WindowContext for Window {
 	fn event(&self) -> Option<Event>
 	{
-		let event = self.connection.wait_for_event();
-		self.window_event_map(event)
+		self.demux.wait(self.window).or_else(|| self.disconnected())
 	}
 
 	fn poll(&self) -> Option<Event>
 	{
-		let event = self.connection.poll_for_event();
-		self.window_event_map(event)
+		self.demux.poll(self.window).or_else(|| self.disconnected())
+	}
+
+	fn event_timeout(&self, timeout: Duration) -> Option<Event>
+	{
+		let deadline = Instant::now() + timeout;
+		self.demux.wait_until(self.window, deadline).or_else(|| self.disconnected())
 	}
 
 	fn stat(&self, status: Stat) -> Option<Data>
 	{
-		use crate::{stat::{WindowStat, XcbStat}, data::{WindowData, XcbData}};
+		use crate::{stat::XcbStat, data::XcbData};
 
 		match status {
-			Stat::Window(status) => {
-				Some((match status {
-					WindowStat::Position => WindowData::Position(self.stat_position()?),
-					WindowStat::Dimension => WindowData::Dimension(self.stat_dimension()?),
-					WindowStat::Depth => WindowData::Depth(self.stat_depth()?)
-				}).into())
+			Stat::Window(WindowStat::Stacking) => {
+				Some(WindowData::Stacking(self.stat_stacking()?).into())
 			},
+			Stat::Window(status) => self.pending(status)?.wait(),
 			Stat::Xcb(status) => {
 				Some((match status {
 					XcbStat::Connection => XcbData::Connection(Arc::clone(&self.connection)),
 					XcbStat::Window => XcbData::Window(self.window),
 					XcbStat::VisualType => XcbData::VisualType(self.screen.visual?),
-					XcbStat::Pixmap(w, h) => XcbData::Pixmap(self.create_pixmap(w, h)?)
+					XcbStat::Screen => XcbData::Screen(self.screen.number),
+					XcbStat::Pixmap(w, h) => XcbData::Pixmap(self.create_pixmap(false, w, h).ok()??)
 				}).into())
-			}
+			},
+			_ => None
+		}
+	}
+
+	fn stat_checked(&self, status: Stat) -> Result<Option<Data>, Error>
+	{
+		use crate::{stat::XcbStat, data::XcbData};
+
+		match status {
+			Stat::Xcb(XcbStat::Pixmap(w, h)) => {
+				let pixmap = self.create_pixmap(true, w, h).map_err(protocol_error)?;
+				Ok(pixmap.map(|pixmap| XcbData::Pixmap(pixmap).into()))
+			},
+			status => Ok(self.stat(status))
 		}
 	}
 
 	fn window(&self, command: &WindowCommand)
 	{
-		use WindowCommand::*;
-		match command {
-			Title(name) => self.title(name),
-			Dimension((w, h)) => self.resize(*w, *h),
-			Origin((x, y)) => self.move_to(*x, *y),
-			Map => self.map(),
-			Unmap => self.unmap(),
-			StackAbove => self.stack_above(),
-			StackBelow => self.stack_below(),
-			Clear => self.clear(),
-			Update => { self.update(); }
+		// Errors of unchecked requests are not reported,
+		// the demux drops them as they belong to no window
+		let _ = self.command(false, command);
+	}
+
+	fn validate(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		if let WindowCommand::Dimension((0, _)) | WindowCommand::Dimension((_, 0)) = command {
+			return Err(Error::Protocol {
+				code: xcb::VALUE,
+				major: xcb::CONFIGURE_WINDOW,
+				resource: self.window
+			});
 		}
+		Ok(())
+	}
+
+	fn window_checked(&self, command: &WindowCommand) -> Result<(), Error>
+	{
+		self.command(true, command).map_err(protocol_error)
 	}
 	
 	fn update(&self) {
 		self.connection.flush();
 	}
+
+	fn closed(&self) -> bool {
+		self.destroyed.load(Ordering::Relaxed) ||
+			self.demux.destroyed(self.window) ||
+			self.connection.has_error().is_err()
+	}
 }
 
-impl From<&Connection> for Window {
-	fn from(c: &Connection) -> Self {
+impl Window {
+	/// Create a window, inside `parent` if there is one
+	pub fn create(c: &Connection, attributes: &WindowAttributes, parent: Option<&Window>) -> Self {
 		let setup = c.connection.get_setup();
 		let screen = setup.roots().nth(c.preference as usize).unwrap();
-		let window = window(&c, &screen);
-		Self::new(window, c.connection.clone(), c.screen.clone(), c.delete)
+		let embedded = parent.is_none() && attributes.embed.is_some();
+		let parent = parent.map(|p| p.window)
+			.or(attributes.embed)
+			.unwrap_or(screen.root());
+		let window = window(c, &screen, parent, attributes);
+		let screen = match attributes.visual {
+			None => c.screen.clone(),
+			Some((visual, depth)) => Screen::with_visual(&screen, c.preference, depth, visual)
+		};
+		let window = Self::new(window, c.connection.clone(), screen, Arc::clone(&c.demux), embedded);
+		if embedded {
+			let _ = window.embed_info(false, 0);
+		}
+		window
+	}
+}
+
+impl Window {
+	/// Wrap the existing `window` of another client. The window
+	/// is not destroyed when dropped.
+	pub fn adopt(c: &Connection, window: xcb::Window) -> Result<Self, Error> {
+		let attributes = xcb::get_window_attributes(&c.connection, window);
+		let geometry = xcb::get_geometry(&c.connection, window);
+		let attributes = attributes.get_reply().map_err(protocol_error)?;
+		let geometry = geometry.get_reply().map_err(protocol_error)?;
+
+		let setup = c.connection.get_setup();
+		let screen = match setup.roots().enumerate().find(|(_, s)| s.root() == geometry.root()) {
+			None => c.screen.clone(),
+			Some((number, screen)) => {
+				Screen::with_visual(&screen, number as i32, geometry.depth(), attributes.visual())
+			}
+		};
+
+		// Only one client may select button presses
+		let mut mask = event_mask(EventSet::all());
+		if attributes.all_event_masks() & !attributes.your_event_mask() & xcb::EVENT_MASK_BUTTON_PRESS != 0 {
+			mask &= !xcb::EVENT_MASK_BUTTON_PRESS;
+		}
+		xcb::change_window_attributes_checked(&c.connection, window, &[(xcb::CW_EVENT_MASK, mask)])
+			.request_check()
+			.map_err(protocol_error)?;
+
+		let mut window = Self::new(window, c.connection.clone(), screen, Arc::clone(&c.demux), false);
+		window.adopted = true;
+		Ok(window)
+	}
+}
+
+impl From<&Connection> for Window {
+	fn from(c: &Connection) -> Self {
+		Self::create(c, &WindowAttributes::default(), None)
 	}
 }
 
 impl Drop for Window {
 	fn drop(&mut self) {
-		xcb::destroy_window(&self.connection, self.window);
+		self.demux.unregister(self.window);
+		if self.destroyed.load(Ordering::Relaxed) {
+			return;
+		}
+		if self.adopted {
+			// Leave the window to its owner
+			xcb::change_window_attributes(&self.connection, self.window,
+				&[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_NO_EVENT)]);
+		} else {
+			xcb::destroy_window(&self.connection, self.window);
+		}
 	}
 }
 
-#[allow(unused_parens)]
-static EVENT_MASK: xcb::EventMask = (
-	xcb::EVENT_MASK_EXPOSURE |
-	xcb::EVENT_MASK_KEY_PRESS |
-	xcb::EVENT_MASK_BUTTON_PRESS |
-	xcb::EVENT_MASK_BUTTON_RELEASE |
-	xcb::EVENT_MASK_POINTER_MOTION |
-	xcb::EVENT_MASK_BUTTON_MOTION |
-	xcb::EVENT_MASK_BUTTON_1_MOTION |
-	xcb::EVENT_MASK_BUTTON_2_MOTION |
-	xcb::EVENT_MASK_BUTTON_3_MOTION |
-	xcb::EVENT_MASK_BUTTON_4_MOTION |
-	xcb::EVENT_MASK_BUTTON_5_MOTION |
-	xcb::EVENT_MASK_ENTER_WINDOW |
-	xcb::EVENT_MASK_LEAVE_WINDOW |
-	xcb::EVENT_MASK_FOCUS_CHANGE |
-	/*xcb::EVENT_MASK_RESIZE_REDIRECT |*/
-	xcb::EVENT_MASK_STRUCTURE_NOTIFY |
-	xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY |
-	/*xcb::EVENT_MASK_SUBSTRUCTURE_REDIRECT |*/
-	xcb::EVENT_MASK_VISIBILITY_CHANGE
-);
-
-#[inline]
-fn event_type(e: &xcb::base::GenericEvent) -> u8
+/// The event mask subscribing to `events`
+fn event_mask(events: EventSet) -> xcb::EventMask
 {
-	e.response_type() & !0x080
+	let masks = [
+		(EventSet::KEY, xcb::EVENT_MASK_KEY_PRESS | xcb::EVENT_MASK_KEY_RELEASE),
+		(EventSet::BUTTON, xcb::EVENT_MASK_BUTTON_PRESS | xcb::EVENT_MASK_BUTTON_RELEASE),
+		(EventSet::MOTION, xcb::EVENT_MASK_POINTER_MOTION),
+		(EventSet::DRAG, xcb::EVENT_MASK_BUTTON_MOTION),
+		(EventSet::CROSSING, xcb::EVENT_MASK_ENTER_WINDOW | xcb::EVENT_MASK_LEAVE_WINDOW),
+		(EventSet::FOCUS, xcb::EVENT_MASK_FOCUS_CHANGE),
+		(EventSet::EXPOSURE, xcb::EVENT_MASK_EXPOSURE),
+		(EventSet::STRUCTURE, xcb::EVENT_MASK_STRUCTURE_NOTIFY)
+	];
+	masks.iter()
+		.filter(|(set, _)| events.contains(*set))
+		.fold(xcb::EVENT_MASK_NO_EVENT, |mask, (_, m)| mask | m)
 }
 
-fn window(conn: &Connection, screen: &xcb::Screen) -> u32
+fn window(conn: &Connection, screen: &xcb::Screen,
+	parent: xcb::Window, attributes: &WindowAttributes) -> u32
 {
 	let id = conn.generate_id();
 
 	let root = screen.root();
-	let visual = screen.root_visual();
+	let (visual, depth) = attributes.visual
+		.unwrap_or((screen.root_visual(), xcb::COPY_FROM_PARENT as u8));
 	let cmid = conn.generate_id();
 	xcb::create_colormap(conn, xcb::COLORMAP_ALLOC_NONE as u8, cmid, root, visual);
 
-	let values = [
-		(xcb::CW_EVENT_MASK, EVENT_MASK),
+	let mask = event_mask(attributes.events.unwrap_or(EventSet::all()));
+	let mut values = vec![
+		(xcb::CW_EVENT_MASK, mask),
 		(xcb::CW_BORDER_PIXEL, 0),
 		(xcb::CW_COLORMAP, cmid)
 	];
+	if let Some(pixel) = attributes.background {
+		values.push((xcb::CW_BACK_PIXEL, pixel));
+	}
+	if attributes.override_redirect {
+		values.push((xcb::CW_OVERRIDE_REDIRECT, 1));
+	}
 
-	let (x, y) = (0, 0);
-	let (width, height) = (1, 1);
-	let border = 0;
+	let (x, y) = attributes.origin;
+	let (width, height) = attributes.dimension;
 
 	xcb::create_window(
 		conn,
-		xcb::COPY_FROM_PARENT as u8,
+		depth,
 		id,
-		root,
+		parent,
 		x as i16,
 		y as i16,
 		width as u16,
 		height as u16,
-		border,
+		attributes.border,
 		xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
 		visual,
 		&values
 	);
+
+	use xcb::*;
+	if let Some(title) = &attributes.title {
+		change_property(conn, PROP_MODE_REPLACE as u8, id, ATOM_WM_NAME, ATOM_STRING, 8, title.as_bytes());
+	}
+	if let Some((instance, class)) = &attributes.class {
+		let class = format!("{}\0{}\0", instance, class);
+		change_property(conn, PROP_MODE_REPLACE as u8, id, ATOM_WM_CLASS, ATOM_STRING, 8, class.as_bytes());
+	}
+
+	// Ask the window manager for a close request instead of killing the client
+	let atoms = conn.demux.atoms();
+	if let (true, Some(protocols), Some(delete)) = (parent == root, atoms.protocols, atoms.delete) {
+		change_property(conn, PROP_MODE_REPLACE as u8, id, protocols, ATOM_ATOM, 32, &[delete]);
+	}
 	id
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
+
+	#[test]
+	fn display() {
+		assert_eq!(parse_display(":1"), Some(("", 1, None)));
+		assert_eq!(parse_display("host:2.1"), Some(("host", 2, Some(1))));
+		assert_eq!(parse_display("not a display"), None);
+		assert_eq!(parse_display(":1.x"), None);
+	}
+
+	#[test]
+	fn unreachable() {
+		// No display server listens on these displays
+		assert_eq!(probe(":4242"), Some(false));
+		assert_eq!(probe("127.0.0.1:4242"), Some(false));
+		assert_eq!(probe("not a display"), None);
+
+		let error = connection_error(":4242", xcb::ConnError::Connection);
+		assert_eq!(error, ConnectionError::Unreachable(":4242".into()));
+	}
 	
 	#[test]
+	#[ignore = "requires an X server"]
 	fn xcb_connection() {
 		let connect = Connection::open().unwrap();
 		let window = Window::from(&connect);
-		window.resize(150, 150);
-		window.map();
+		window.resize(true, 150, 150).unwrap();
+		window.map(true).unwrap();
 		connect.flush();
 		let timeout = std::time::Duration::from_millis(5000);
 		std::thread::sleep(timeout);
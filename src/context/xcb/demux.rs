@@ -0,0 +1,473 @@
+use std::{
+	collections::{HashMap, HashSet, VecDeque},
+	sync::{Arc, Mutex, MutexGuard, Condvar},
+	time::Instant,
+	os::unix::io::AsRawFd
+};
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+use std::task::Waker;
+use crate::{
+	Event, DisplayEvent, KeyEvent, MouseEvent, event,
+	event::{FocusEvent, EmbedEvent, input::MouseData}
+};
+use super::xembed;
+
+#[derive(Default)]
+struct Queues {
+	windows: HashMap<xcb::Window, VecDeque<Event>>,
+	destroyed: HashSet<xcb::Window>,
+	/// Embedded clients and the window hosting them
+	embedded: HashMap<xcb::Window, xcb::Window>,
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	wakers: HashMap<xcb::Window, Waker>,
+	reading: bool
+}
+
+/// The atoms used by every window of a connection
+pub struct Atoms {
+	pub protocols: Option<xcb::Atom>,
+	pub delete: Option<xcb::Atom>,
+	pub wake: Option<xcb::Atom>,
+	pub xembed: Option<xcb::Atom>,
+	pub xembed_info: Option<xcb::Atom>
+}
+
+impl Atoms {
+	pub fn intern(connection: &xcb::Connection) -> Self {
+		// Send every request before waiting on the replies
+		let cookies = ["WM_PROTOCOLS", "WM_DELETE_WINDOW", "_REN_WAKE", "_XEMBED", "_XEMBED_INFO"]
+			.map(|name| xcb::intern_atom(connection, false, name));
+		let [protocols, delete, wake, xembed, xembed_info] =
+			cookies.map(|cookie| cookie.get_reply().ok().map(|r| r.atom()));
+		Self {
+			protocols,
+			delete,
+			wake,
+			xembed,
+			xembed_info
+		}
+	}
+}
+
+/// How long to block for an event
+#[derive(Copy, Clone)]
+enum Block {
+	No,
+	Forever,
+	Until(Instant)
+}
+
+/// Reads events from the shared connection and routes
+/// each one to the queue of the window it belongs to.
+/// Only one thread reads from the connection at a time,
+/// the others wait until an event is routed to them.
+pub struct Demux {
+	connection: Arc<xcb::Connection>,
+	atoms: Atoms,
+	/// A hidden window of this connection that receives the wake messages
+	wake_window: xcb::Window,
+	queues: Mutex<Queues>,
+	ready: Condvar
+}
+
+impl Demux {
+	pub fn new(connection: Arc<xcb::Connection>, atoms: Atoms, root: xcb::Window) -> Self {
+		// Never mapped, it only exists so the wake messages have
+		// a destination that is always owned by this connection
+		let wake_window = connection.generate_id();
+		xcb::create_window(&connection, xcb::COPY_FROM_PARENT as u8, wake_window, root, -1, -1, 1, 1, 0,
+			xcb::WINDOW_CLASS_INPUT_ONLY as u16, xcb::COPY_FROM_PARENT, &[]);
+		Self {
+			connection,
+			atoms,
+			wake_window,
+			queues: Mutex::new(Queues::default()),
+			ready: Condvar::new()
+		}
+	}
+
+	pub fn atoms(&self) -> &Atoms {
+		&self.atoms
+	}
+
+	fn lock(&self) -> MutexGuard<'_, Queues> {
+		self.queues.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Start routing events for `window`
+	pub fn register(&self, window: xcb::Window) {
+		self.lock().windows.insert(window, VecDeque::new());
+	}
+
+	/// Stop routing events for `window` and drop any pending
+	pub fn unregister(&self, window: xcb::Window) {
+		let mut queues = self.lock();
+		queues.windows.remove(&window);
+		queues.destroyed.remove(&window);
+		queues.embedded.retain(|_, host| *host != window);
+		#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+		queues.wakers.remove(&window);
+	}
+
+	/// Follow the `_XEMBED_INFO` of `client`, embedded into `host`
+	pub fn embed(&self, client: xcb::Window, host: xcb::Window) {
+		self.lock().embedded.insert(client, host);
+	}
+
+	/// Check if `window` was destroyed
+	pub fn destroyed(&self, window: xcb::Window) -> bool {
+		self.lock().destroyed.contains(&window)
+	}
+
+	/// Wake `waker` once an event is routed to `window`
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn register_waker(&self, window: xcb::Window, waker: &Waker) {
+		self.lock().wakers.insert(window, waker.clone());
+	}
+
+	/// Queue `event` for `window` as if it was read from the connection.
+	/// Fails if the window is not registered.
+	pub fn post(&self, window: xcb::Window, event: Event) -> bool {
+		let mut queues = self.lock();
+		match queues.windows.get_mut(&window) {
+			None => return false,
+			Some(queue) => queue.push_back(event)
+		}
+		#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+		if let Some(waker) = queues.wakers.remove(&window) {
+			waker.wake();
+		}
+		self.ready.notify_all();
+
+		// The reader is blocked on the connection, so send it
+		// a message to make it look at the queues again
+		if queues.reading {
+			if let Some(wake) = self.atoms.wake {
+				let data = xcb::ClientMessageData::from_data32([0; 5]);
+				let message = xcb::ClientMessageEvent::new(32, self.wake_window, wake, data);
+				xcb::send_event(&self.connection, false, self.wake_window, xcb::EVENT_MASK_NO_EVENT, &message);
+				self.connection.flush();
+			}
+		}
+		true
+	}
+
+	/// Wait for an event of `window`
+	pub fn wait(&self, window: xcb::Window) -> Option<Event> {
+		self.next(Block::Forever, |q| q.windows.get_mut(&window)?.pop_front())
+	}
+
+	/// Wait for an event of `window` until `deadline`
+	pub fn wait_until(&self, window: xcb::Window, deadline: Instant) -> Option<Event> {
+		self.next(Block::Until(deadline), |q| q.windows.get_mut(&window)?.pop_front())
+	}
+
+	/// Poll for an event of `window`
+	pub fn poll(&self, window: xcb::Window) -> Option<Event> {
+		self.next(Block::No, |q| q.windows.get_mut(&window)?.pop_front())
+	}
+
+	/// Wait for an event of any window, until `deadline` if any
+	pub fn wait_any(&self, deadline: Option<Instant>) -> Option<(xcb::Window, Event)> {
+		let block = deadline.map_or(Block::Forever, Block::Until);
+		self.next(block, Self::pop_any)
+	}
+
+	/// Poll for an event of any window
+	pub fn poll_any(&self) -> Option<(xcb::Window, Event)> {
+		self.next(Block::No, Self::pop_any)
+	}
+
+	fn pop_any(queues: &mut Queues) -> Option<(xcb::Window, Event)> {
+		queues.windows.iter_mut()
+			.find_map(|(w, q)| q.pop_front().map(|e| (*w, e)))
+	}
+
+	fn next<T, F>(&self, block: Block, mut take: F) -> Option<T>
+		where F: FnMut(&mut Queues) -> Option<T> {
+		let mut queues = self.lock();
+		loop {
+			if let Some(event) = take(&mut queues) {
+				return Some(event);
+			}
+
+			if queues.reading {
+				queues = match block {
+					Block::No => return None,
+					Block::Forever => {
+						self.ready.wait(queues).unwrap_or_else(|e| e.into_inner())
+					},
+					Block::Until(deadline) => {
+						let timeout = deadline.checked_duration_since(Instant::now())?;
+						self.ready.wait_timeout(queues, timeout)
+							.unwrap_or_else(|e| e.into_inner()).0
+					}
+				};
+				continue;
+			}
+
+			queues.reading = true;
+			drop(queues);
+			let event = match block {
+				Block::No => self.connection.poll_for_event(),
+				Block::Forever => self.connection.wait_for_event(),
+				Block::Until(deadline) => self.wait_for_event_until(deadline)
+			};
+			queues = self.lock();
+			queues.reading = false;
+			self.ready.notify_all();
+
+			// Either there is no event or the connection failed
+			let event = event?;
+
+			// Route every event libxcb has buffered. The socket is no longer
+			// readable, so a stream would not be woken to read them.
+			let mut embedded = Vec::new();
+			let mut next = Some(event);
+			while let Some(event) = next {
+				embedded.extend(self.route(&mut queues, &event));
+				next = self.connection.poll_for_event();
+			}
+			#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+			wake(&mut queues);
+
+			if !embedded.is_empty() {
+				drop(queues);
+				embedded.into_iter().for_each(|client| self.map_embedded(client));
+				queues = self.lock();
+			}
+		}
+	}
+
+	/// Queue an event read from the connection for the window it belongs
+	/// to. Returns the embedded client to map if its `_XEMBED_INFO` changed.
+	fn route(&self, queues: &mut Queues, event: &xcb::GenericEvent) -> Option<xcb::Window> {
+		if self.is_wake(event) {
+			return None;
+		}
+		if let Some(client) = self.embed_info_changed(queues, event) {
+			return Some(client);
+		}
+		let window = event_window(event)?;
+		let queue = queues.windows.get_mut(&window)?;
+		queue.push_back(self.window_event_map(event));
+		if destroyed_window(event) == Some(window) {
+			queues.destroyed.insert(window);
+		}
+		None
+	}
+
+	/// Wait for an event on the connection until `deadline`
+	fn wait_for_event_until(&self, deadline: Instant) -> Option<xcb::GenericEvent> {
+		loop {
+			if let Some(event) = self.connection.poll_for_event() {
+				return Some(event);
+			}
+			if self.connection.has_error().is_err() {
+				return None;
+			}
+
+			let timeout = deadline.checked_duration_since(Instant::now())?;
+			let timeout = timeout.as_micros().div_ceil(1000).min(i32::MAX as u128);
+			let mut fd = libc::pollfd {
+				fd: self.connection.as_raw_fd(),
+				events: libc::POLLIN,
+				revents: 0
+			};
+			unsafe { libc::poll(&mut fd, 1, timeout as libc::c_int) };
+		}
+	}
+
+	/// The embedded client whose `_XEMBED_INFO` changed
+	fn embed_info_changed(&self, queues: &Queues, e: &xcb::GenericEvent) -> Option<xcb::Window> {
+		if event_type(e) != xcb::PROPERTY_NOTIFY {
+			return None;
+		}
+		let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(e) };
+		if Some(event.atom()) != self.atoms.xembed_info || !queues.embedded.contains_key(&event.window()) {
+			return None;
+		}
+		Some(event.window())
+	}
+
+	/// Map or unmap an embedded client following the
+	/// `MAPPED` flag of its `_XEMBED_INFO`
+	fn map_embedded(&self, client: xcb::Window) {
+		let info = match self.atoms.xembed_info {
+			None => return,
+			Some(info) => info
+		};
+		let cookie = xcb::get_property(&self.connection, false, client, info, info, 0, 2);
+		let flags = match cookie.get_reply() {
+			Ok(reply) => match reply.value::<u32>() {
+				[_, flags] => *flags,
+				_ => return
+			},
+			Err(_) => return
+		};
+		if flags & xembed::MAPPED != 0 {
+			xcb::map_window(&self.connection, client);
+		} else {
+			xcb::unmap_window(&self.connection, client);
+		}
+		self.connection.flush();
+	}
+
+	/// Check if the event was only sent to wake the reader
+	fn is_wake(&self, e: &xcb::GenericEvent) -> bool {
+		if event_type(e) != xcb::CLIENT_MESSAGE {
+			return false;
+		}
+		let event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(e) };
+		self.atoms.wake == Some(event.type_())
+	}
+
+	#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(response = event_type(e)), ret))]
+	pub fn window_event_map(&self, e: &xcb::GenericEvent) -> Event {
+		let response = event_type(e);
+
+		match response {
+			xcb::EXPOSE => {
+				DisplayEvent::Expose(event::xcb::expose(e)).into()
+			},
+
+			xcb::KEY_PRESS => {
+				KeyEvent::Press(event::xcb::key_press(e)).into()
+			},
+
+			xcb::KEY_RELEASE => {
+				KeyEvent::Release(event::xcb::key_release(e)).into()
+			},
+
+			xcb::BUTTON_PRESS => {
+				let (pos, button) = event::xcb::button_press(e);
+				MouseEvent::Press(MouseData::new(button, pos)).into()
+			},
+
+			xcb::BUTTON_RELEASE => {
+				let (pos, button) = event::xcb::button_release(e);
+				MouseEvent::Release(MouseData::new(button, pos)).into()
+			},
+
+			xcb::MOTION_NOTIFY => {
+				MouseEvent::Move(event::xcb::mouse_move(e)).into()
+			},
+
+			xcb::ENTER_NOTIFY => {
+				MouseEvent::Enter(event::xcb::mouse_enter(e)).into()
+			},
+
+			xcb::LEAVE_NOTIFY => {
+				MouseEvent::Leave(event::xcb::mouse_leave(e)).into()
+			},
+
+			xcb::FOCUS_IN => DisplayEvent::from(FocusEvent::Gain).into(),
+
+			xcb::FOCUS_OUT => DisplayEvent::from(FocusEvent::Lose).into(),
+
+			xcb::CONFIGURE_NOTIFY => {
+				let event = unsafe { xcb::cast_event::<xcb::ConfigureNotifyEvent>(e) };
+				// A child window was configured
+				if event.window() != event.event() {
+					return Event::Unknown(Some(response.into()));
+				}
+				DisplayEvent::Resize((event.width(), event.height())).into()
+			},
+
+			xcb::DESTROY_NOTIFY if destroyed_window(e) == event_window(e) => {
+				Event::Terminate
+			},
+
+			xcb::CLIENT_MESSAGE => {
+				let event = unsafe { xcb::cast_event::<xcb::ClientMessageEvent>(e) };
+				let data = event.data().data32();
+				let ty = Some(event.type_());
+				if event.format() == 32 && ty == self.atoms.protocols && Some(data[0]) == self.atoms.delete {
+					return Event::CloseRequested;
+				}
+				if event.format() == 32 && ty == self.atoms.xembed {
+					if let Some(event) = embed_event(data) {
+						return event;
+					}
+				}
+				Event::Unknown(Some(response.into()))
+			},
+
+			_ => Event::Unknown(Some(response.into()))
+		}
+	}
+}
+
+/// Wake the streams of every window with queued events
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+fn wake(queues: &mut Queues)
+{
+	let Queues { windows, wakers, .. } = queues;
+	wakers.retain(|window, waker| {
+		let queued = windows.get(window).is_some_and(|queue| !queue.is_empty());
+		if queued {
+			waker.wake_by_ref();
+		}
+		!queued
+	});
+}
+
+/// Map an XEmbed message, the data is `[time, message, detail, data1, data2]`
+fn embed_event(data: &[u32]) -> Option<Event>
+{
+	Some(match data[1] {
+		xembed::EMBEDDED_NOTIFY => DisplayEvent::from(EmbedEvent::Embedded(data[3])).into(),
+		xembed::WINDOW_ACTIVATE => DisplayEvent::from(EmbedEvent::Activate).into(),
+		xembed::WINDOW_DEACTIVATE => DisplayEvent::from(EmbedEvent::Deactivate).into(),
+		xembed::FOCUS_IN => DisplayEvent::from(FocusEvent::Gain).into(),
+		xembed::FOCUS_OUT => DisplayEvent::from(FocusEvent::Lose).into(),
+		xembed::MODALITY_ON => DisplayEvent::from(EmbedEvent::ModalityOn).into(),
+		xembed::MODALITY_OFF => DisplayEvent::from(EmbedEvent::ModalityOff).into(),
+		_ => return None
+	})
+}
+
+#[inline]
+fn event_type(e: &xcb::GenericEvent) -> u8
+{
+	e.response_type() & !0x080
+}
+
+/// The window destroyed by a `DestroyNotify`, which may be a child
+/// of the window the event was delivered to
+fn destroyed_window(e: &xcb::GenericEvent) -> Option<xcb::Window>
+{
+	match event_type(e) {
+		xcb::DESTROY_NOTIFY => {
+			Some(unsafe { xcb::cast_event::<xcb::DestroyNotifyEvent>(e) }.window())
+		},
+		_ => None
+	}
+}
+
+/// The window an event was delivered to
+fn event_window(e: &xcb::GenericEvent) -> Option<xcb::Window>
+{
+	use xcb::*;
+
+	Some(unsafe {
+		match event_type(e) {
+			KEY_PRESS | KEY_RELEASE => cast_event::<KeyPressEvent>(e).event(),
+			BUTTON_PRESS | BUTTON_RELEASE => cast_event::<ButtonPressEvent>(e).event(),
+			MOTION_NOTIFY => cast_event::<MotionNotifyEvent>(e).event(),
+			ENTER_NOTIFY | LEAVE_NOTIFY => cast_event::<EnterNotifyEvent>(e).event(),
+			FOCUS_IN | FOCUS_OUT => cast_event::<FocusInEvent>(e).event(),
+			EXPOSE => cast_event::<ExposeEvent>(e).window(),
+			VISIBILITY_NOTIFY => cast_event::<VisibilityNotifyEvent>(e).window(),
+			DESTROY_NOTIFY => cast_event::<DestroyNotifyEvent>(e).event(),
+			UNMAP_NOTIFY => cast_event::<UnmapNotifyEvent>(e).event(),
+			MAP_NOTIFY => cast_event::<MapNotifyEvent>(e).event(),
+			REPARENT_NOTIFY => cast_event::<ReparentNotifyEvent>(e).event(),
+			CONFIGURE_NOTIFY => cast_event::<ConfigureNotifyEvent>(e).event(),
+			GRAVITY_NOTIFY => cast_event::<GravityNotifyEvent>(e).event(),
+			PROPERTY_NOTIFY => cast_event::<PropertyNotifyEvent>(e).window(),
+			CLIENT_MESSAGE => cast_event::<ClientMessageEvent>(e).window(),
+			_ => return None
+		}
+	})
+}
@@ -1,5 +1,7 @@
 
 mod context;
+mod demux;
+mod xembed;
 
 use super::{ConnectionError, WindowContext};
-pub use context::{Connection, Window};
+pub use context::{Connection, Window, Proxy, Pending};
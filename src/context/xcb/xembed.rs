@@ -0,0 +1,18 @@
+//! Constants of the XEmbed protocol
+
+/// The protocol version implemented
+pub const VERSION: u32 = 0;
+
+/// `_XEMBED_INFO` flag, the client wants to be mapped
+pub const MAPPED: u32 = 1 << 0;
+
+pub const EMBEDDED_NOTIFY: u32 = 0;
+pub const WINDOW_ACTIVATE: u32 = 1;
+pub const WINDOW_DEACTIVATE: u32 = 2;
+pub const FOCUS_IN: u32 = 4;
+pub const FOCUS_OUT: u32 = 5;
+pub const MODALITY_ON: u32 = 10;
+pub const MODALITY_OFF: u32 = 11;
+
+/// `FOCUS_IN` detail, keep the focus where it was in the client
+pub const FOCUS_CURRENT: u32 = 0;
@@ -15,10 +15,12 @@ macro_rules! display_from {
 /// A map of an area of the window
 /// that needs to be redrawn.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Map(pub Position, pub Dimension);
 
 /// Window focus event
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FocusEvent {
 	/// The window gained focus
 	Gain,
@@ -26,4 +28,23 @@ pub enum FocusEvent {
 	Lose
 }
 
-display_from!(FocusEvent, Focus);
\ No newline at end of file
+display_from!(FocusEvent, Focus);
+
+/// An event of the XEmbed protocol, for a window
+/// embedded into a foreign window
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EmbedEvent {
+	/// The window was embedded. Contains the id of the embedder window
+	Embedded(u32),
+	/// The embedder window became active
+	Activate,
+	/// The embedder window became inactive
+	Deactivate,
+	/// The embedder is blocked by a modal dialog
+	ModalityOn,
+	/// The embedder is no longer blocked by a modal dialog
+	ModalityOff
+}
+
+display_from!(EmbedEvent, Embed);
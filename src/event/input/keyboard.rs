@@ -4,6 +4,7 @@ use std::collections::HashSet;
 /// Possible keyboard modifiers/modes
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Modifier {
 	Ctrl,
 	Shift,
@@ -21,9 +22,65 @@ pub type Modifiers = &'static [Modifier];
 /// Converts a `KeyCode` into a `KeyMap`
 pub(crate) type Mapping = fn(KeyCode) -> Option<KeyMap>;
 
+/// The modifier set of the X key and button `state` mask
+#[cfg(any(target_family = "unix", feature = "serde"))]
+pub(crate) fn modifier(state: u16) -> Option<Modifiers> {
+	use Modifier::*;
+	Some(match state {
+		0x01 => &[Shift],
+		0x02 => &[Caps],
+		0x03 => &[Shift, Caps],
+		0x04 => &[Ctrl],
+		0x05 => &[Ctrl, Shift],
+		0x06 => &[Ctrl, Caps],
+		0x07 => &[Ctrl, Shift, Caps],
+		0x08 => &[Alt],
+		0x09 => &[Alt, Shift],
+		0x0A => &[Alt, Caps],
+		0x0B => &[Alt, Shift, Caps],
+		0x0C => &[Ctrl, Alt],
+		0x0D => &[Ctrl, Alt, Shift],
+		0x0E => &[Ctrl, Alt, Caps],
+		0x0F => &[Ctrl, Alt, Shift, Caps],
+		0x10 => &[NumLock],
+		0x11 => &[NumLock, Shift],
+		0x12 => &[NumLock, Caps],
+		0x13 => &[NumLock, Caps, Shift],
+		0x14 => &[NumLock, Ctrl],
+		0x15 => &[NumLock, Ctrl, Shift],
+		0x16 => &[NumLock, Ctrl, Caps],
+		0x17 => &[NumLock, Ctrl, Shift, Caps],
+		0x18 => &[NumLock, Alt],
+		0x19 => &[NumLock, Alt, Shift],
+		0x1A => &[NumLock, Alt, Caps],
+		0x1B => &[NumLock, Alt, Shift, Caps],
+		0x1C => &[NumLock, Ctrl, Alt],
+		0x1D => &[NumLock, Ctrl, Alt, Shift],
+		0x1E => &[NumLock, Ctrl, Alt, Caps],
+		0x1F => &[NumLock, Ctrl, Alt, Shift, Caps],
+		_ => return None
+	})
+}
+
+/// The modifier set of the combination `mods`
+#[cfg(feature = "serde")]
+pub(crate) fn modifiers(mods: &[Modifier]) -> Option<Modifiers> {
+	use Modifier::*;
+	let state = mods.iter().fold(0, |state, m| state | match m {
+		Shift => 0x01,
+		Caps => 0x02,
+		Ctrl => 0x04,
+		Alt => 0x08,
+		NumLock => 0x10,
+		_ => 0
+	});
+	modifier(state)
+}
+
 /// A mapping of possible keyboard characters.
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyMap {
 	Escape,
 	F1,
@@ -393,8 +450,6 @@ impl super::KeyInput {
 	/// Converts printable characters into a `&str` using the keyboard
 	/// layout `Layout`
 	pub fn as_str_using(&self, layout: &Layout) -> Option<&str> {
-		use Modifier::*;
-		
 		let key = self.map()?;
 		let mods = self.mods.as_ref().map(|m| m.iter().cloned().collect::<HashSet<_>>());
 		layout.as_str(key, mods.as_ref())
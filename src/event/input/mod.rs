@@ -7,7 +7,9 @@ use super::{Event, InputEvent, Position};
 pub use keyboard::{KeyCode, KeyMap};
 
 /// Keyboard input data
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "KeyData", into = "KeyData"))]
 pub struct KeyInput {
 	code: KeyCode,
 	mods: Option<Modifiers>,
@@ -23,6 +25,13 @@ impl KeyInput {
 		}
 	}
 
+	/// Create the input of a key, for example to script an `Event`
+	#[cfg(target_family = "unix")]
+	pub fn from_map(key: KeyMap, mods: Option<Modifiers>) -> Option<Self> {
+		use crate::event::xcb::{keycode, keymap};
+		Some(Self::new(keycode(key)?, mods, keymap))
+	}
+
 	/// Return the keycode
 	pub fn code(&self) -> KeyCode {
 		self.code
@@ -34,8 +43,45 @@ impl KeyInput {
 	}
 }
 
+/// The serialized form of `KeyInput`
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct KeyData {
+	code: KeyCode,
+	mods: Option<Vec<Modifier>>
+}
+
+#[cfg(feature = "serde")]
+impl From<KeyInput> for KeyData {
+	fn from(key: KeyInput) -> Self {
+		Self {
+			code: key.code,
+			mods: key.mods.map(<[Modifier]>::to_vec)
+		}
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<KeyData> for KeyInput {
+	fn from(key: KeyData) -> Self {
+		#[cfg(target_family = "unix")]
+		let map: Mapping = crate::event::xcb::keymap;
+		#[cfg(not(target_family = "unix"))]
+		let map: Mapping = |_| None;
+		let mods = key.mods.and_then(|mods| keyboard::modifiers(&mods));
+		Self::new(key.code, mods, map)
+	}
+}
+
+impl PartialEq for KeyInput {
+	fn eq(&self, other: &Self) -> bool {
+		self.code == other.code && self.mods == other.mods
+	}
+}
+
 /// The type of Key event.
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyEvent {
 	/// A Key press has occured.
 	Press(KeyInput),
@@ -59,6 +105,7 @@ impl From<KeyEvent> for Event {
 
 /// The mouse input type
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseInput {
 	/// Left Mouse button
 	Left,
@@ -76,13 +123,15 @@ pub enum MouseInput {
 
 /// Mouse event data
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MouseData {
 	input: MouseInput,
 	position: Position
 }
 
 impl MouseData {
-	pub(crate) fn new(input: MouseInput, position: Position) -> Self {
+	/// Create mouse event data
+	pub fn new(input: MouseInput, position: Position) -> Self {
 		Self {
 			input,
 			position
@@ -104,6 +153,7 @@ impl MouseData {
 
 /// The type of mouse event
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseEvent {
 	/// A Mouse press has occured.
 	Press(MouseData),
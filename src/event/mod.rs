@@ -21,11 +21,13 @@ pub mod display;
 /// All events relating to user input
 pub mod input;
 
-pub use display::FocusEvent;
+pub use display::{FocusEvent, EmbedEvent};
 
 #[cfg(target_family = "unix")]
 pub(crate) mod xcb;
 
+use crate::{Token, Message};
+
 pub type Coord = i16;
 pub type Size = u16;
 
@@ -44,22 +46,101 @@ macro_rules! event_from {
 	}
 }
 
+/// A set of event categories a window subscribes to
+/// # Example
+/// ```
+/// use ren::event::EventSet;
+///
+/// let events = EventSet::KEY | EventSet::EXPOSURE;
+/// assert!(events.contains(EventSet::KEY));
+/// assert!(!events.contains(EventSet::MOTION));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EventSet(u16);
+
+impl EventSet {
+	/// Key presses and releases
+	pub const KEY: Self = Self(1);
+	/// Mouse button presses and releases
+	pub const BUTTON: Self = Self(1 << 1);
+	/// Every mouse motion
+	pub const MOTION: Self = Self(1 << 2);
+	/// Mouse motion only while a button is held
+	pub const DRAG: Self = Self(1 << 3);
+	/// The mouse entering and leaving the window
+	pub const CROSSING: Self = Self(1 << 4);
+	/// Focus changes
+	pub const FOCUS: Self = Self(1 << 5);
+	/// Areas of the window that need to be updated
+	pub const EXPOSURE: Self = Self(1 << 6);
+	/// Changes to the window, such as resizing. On X this
+	/// is also how the session learns the window was destroyed.
+	pub const STRUCTURE: Self = Self(1 << 7);
+
+	/// A set without any category
+	pub const fn empty() -> Self {
+		Self(0)
+	}
+
+	/// A set with every category
+	pub const fn all() -> Self {
+		Self((1 << 8) - 1)
+	}
+
+	/// If every category of `other` is in the set
+	pub const fn contains(&self, other: Self) -> bool {
+		self.0 & other.0 == other.0
+	}
+
+	/// If the set has no category
+	pub const fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+}
+
+impl std::ops::BitOr for EventSet {
+	type Output = Self;
+
+	fn bitor(self, other: Self) -> Self {
+		Self(self.0 | other.0)
+	}
+}
+
+impl std::ops::BitOrAssign for EventSet {
+	fn bitor_assign(&mut self, other: Self) {
+		self.0 |= other.0;
+	}
+}
+
+impl std::ops::Sub for EventSet {
+	type Output = Self;
+
+	fn sub(self, other: Self) -> Self {
+		Self(self.0 & !other.0)
+	}
+}
+
 /// A display event
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DisplayEvent {
 	/// An area of the window that needs to be updated.
 	Expose(display::Map),
 	/// The window focus changed
 	Focus(FocusEvent),
 	/// The window dimensions changed
-	Resize(Dimension)
+	Resize(Dimension),
+	/// An XEmbed protocol event
+	Embed(display::EmbedEvent)
 }
 
 event_from!(DisplayEvent, Display);
 
 /// An input event
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InputEvent {
 	/// A signal for a Key (KeyBoard) event.
 	Key(input::KeyEvent),
@@ -71,16 +152,31 @@ event_from!(InputEvent, Input);
 
 /// An `Event`
 #[non_exhaustive]
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event {
 	/// An unknown event occurred. May contain an event code
 	Unknown(Option<u16>),
 	/// An event signalling termination of the current application window.
+	/// The window was destroyed and the session is closed.
 	Terminate,
+	/// The user asked to close the window, such as through the window manager.
+	/// The window stays open until it is closed with `WindowCommand::Close`.
+	CloseRequested,
+	/// The connection with the windowing system was lost. It is delivered
+	/// once to each session, after it the session fails with `Error::Closed`.
+	Disconnected,
 	/// A display type event
 	Display(DisplayEvent),
 	/// An input event from a user
-	Input(InputEvent)
+	Input(InputEvent),
+	/// A timer of the session expired. Contains the timer token
+	Timer(Token),
+	/// A user event posted through an `EventProxy`
+	User(String),
+	/// The response to a request sent with `Connection::send_async`.
+	/// The response has the id of the request.
+	Reply(Box<Message>)
 }
 
 impl Event {
@@ -88,21 +184,13 @@ impl Event {
 	/// If the event is a `DisplayEvent`
 	pub fn is_display(&self) -> bool
 	{
-		if let Event::Display(_) = self {
-			true
-		} else {
-			false
-		}
+		matches!(self, Event::Display(_))
 	}
 
 	/// If the event is an `InputEvent`
 	pub fn is_input(&self) -> bool
 	{
-		if let Event::Input(_) = self {
-			true
-		} else {
-			false
-		}
+		matches!(self, Event::Input(_))
 	}
 
 	/// Returns `Some` if a `KeyEvent` otherwise `None`
@@ -15,46 +15,7 @@ pub fn expose(event: &xcb::GenericEvent) -> Map
 	)
 }
 
-fn modifier(state: u16) -> Option<keyboard::Modifiers> {
-	use keyboard::Modifier::*;
-	Some(match state {
-		0x01 => &[Shift],
-		0x02 => &[Caps],
-		0x03 => &[Shift, Caps],
-		0x04 => &[Ctrl],
-		0x05 => &[Ctrl, Shift],
-		0x06 => &[Ctrl, Caps],
-		0x07 => &[Ctrl, Shift, Caps],
-		0x08 => &[Alt],
-		0x09 => &[Alt, Shift],
-		0x0A => &[Alt, Caps],
-		0x0B => &[Alt, Shift, Caps],
-		0x0C => &[Ctrl, Alt],
-		0x0D => &[Ctrl, Alt, Shift],
-		0x0E => &[Ctrl, Alt, Caps],
-		0x0F => &[Ctrl, Alt, Shift, Caps],
-		0x10 => &[NumLock],
-		0x11 => &[NumLock, Shift],
-		0x12 => &[NumLock, Caps],
-		0x13 => &[NumLock, Caps, Shift],
-		0x14 => &[NumLock, Ctrl],
-		0x15 => &[NumLock, Ctrl, Shift],
-		0x16 => &[NumLock, Ctrl, Caps],
-		0x17 => &[NumLock, Ctrl, Shift, Caps],
-		0x18 => &[NumLock, Alt],
-		0x19 => &[NumLock, Alt, Shift],
-		0x1A => &[NumLock, Alt, Caps],
-		0x1B => &[NumLock, Alt, Shift, Caps],
-		0x1C => &[NumLock, Ctrl, Alt],
-		0x1D => &[NumLock, Ctrl, Alt, Shift],
-		0x1E => &[NumLock, Ctrl, Alt, Caps],
-		0x1F => &[NumLock, Ctrl, Alt, Shift, Caps],
-		_ => return None
-	})
-}
-
-#[allow(dead_code)]
-fn keymap(code: KeyCode) -> Option<KeyMap> {
+pub fn keymap(code: KeyCode) -> Option<KeyMap> {
 	use KeyMap::*;
 	Some(match code {
 		0x09 => Escape,
@@ -163,12 +124,18 @@ fn keymap(code: KeyCode) -> Option<KeyMap> {
 	})
 }
 
+/// Find the keycode that maps to `key`
+pub fn keycode(key: KeyMap) -> Option<KeyCode>
+{
+	(0..=0xFF).find(|code| keymap(*code) == Some(key))
+}
+
 pub fn key_press(event: &xcb::GenericEvent) -> KeyInput
 {
 	let key: &xcb::KeyPressEvent = unsafe {
 		xcb::cast_event(event)
 	};
-	let mods = modifier(key.state());
+	let mods = keyboard::modifier(key.state());
 	KeyInput::new(key.detail() as KeyCode, mods, keymap)
 }
 
@@ -178,7 +145,7 @@ pub fn key_release(event: &xcb::GenericEvent) -> KeyInput
 		xcb::cast_event(event)
 	};
 
-	let mods = modifier(key.state());
+	let mods = keyboard::modifier(key.state());
 	KeyInput::new(key.detail() as KeyCode, mods, keymap)
 }
 
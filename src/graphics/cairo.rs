@@ -1,33 +1,57 @@
 
 use std::{fs::File, sync::Arc, path::Path, collections::HashMap};
-use crate::{Token, Data, Body, render::context};
+use crate::{Token, Data, Body, SystemType, render::context, data::HeadlessSurface};
 
 pub use cairo::*;
 
-/// Cairo surface
-#[cfg(target_family = "unix")]
-pub type Surface = XCBSurface;
-
 /// Get a cairo window surface from a `Connection`
-#[cfg(target_family = "unix")]
 pub(crate) fn surface(connect: &crate::Connection, window: &Token,
 	(width, height): (i32, i32)) -> Option<Surface>
 {
-	xcb_surface(connect, window, width, height)
+	match connect.system() {
+		#[cfg(target_family = "unix")]
+		SystemType::Xcb => xcb_surface(connect, window, width, height).map(|s| (*s).clone()),
+		_ => None
+	}
 }
 
 /// Get a cairo surface from a `Connection`
-#[cfg(target_family = "unix")]
 pub(crate) fn surface_buffer(connect: &crate::Connection, window: &Token,
 	(width, height): (i32, i32)) -> Option<(Surface, SurfaceContext)>
 {
-	xcb_pixmap_surface(connect, window, width, height)
+	match connect.system() {
+		#[cfg(target_family = "unix")]
+		SystemType::Xcb => {
+			xcb_pixmap_surface(connect, window, width, height).map(|(s, c)| ((*s).clone(), c))
+		},
+		SystemType::Headless => {
+			let surface = ImageSurface::create(Format::ARgb32, width, height).ok()?;
+			Some(((*surface).clone(), SurfaceContext::Headless))
+		},
+		_ => None
+	}
+}
+
+/// Get an image surface holding the pixels of a headless window,
+/// with the window surface to copy the drawing back to
+pub(crate) fn headless_surface(connect: &crate::Connection, token: &Token,
+	(width, height): (i32, i32)) -> Option<(Surface, HeadlessSurface)>
+{
+	use crate::{data::HeadlessData, stat::HeadlessStat};
+
+	let window = match connect.request(token, HeadlessStat::Surface).ok()?.take_body() {
+		Body::Data(Data::Headless(HeadlessData::Surface(s))) => s,
+		_ => return None
+	};
+	let surface = ImageSurface::create(Format::ARgb32, width, height).ok()?;
+	window.copy_to(&surface);
+	Some(((*surface).clone(), window))
 }
 
 /// Create cairo xcb surface
 #[cfg(target_family = "unix")]
 fn xcb_surface_create(connect: &crate::Connection, token: &Token, id: u32,
-	width: i32, height: i32) -> Option<Surface> {
+	width: i32, height: i32) -> Option<XCBSurface> {
 	use crate::{data::XcbData, stat::XcbStat::*};
 	
 	let conn = match connect.request(&token, Connection).ok()?.take_body() {
@@ -50,7 +74,7 @@ fn xcb_surface_create(connect: &crate::Connection, token: &Token, id: u32,
 /// Get cairo xcb surface from a `Connection`
 #[cfg(target_family = "unix")]
 fn xcb_surface(connect: &crate::Connection,
-		   token: &Token, width: i32, height: i32) -> Option<Surface>
+		   token: &Token, width: i32, height: i32) -> Option<XCBSurface>
 {
 	use crate::{data::XcbData, stat::XcbStat::*};
 
@@ -64,7 +88,8 @@ fn xcb_surface(connect: &crate::Connection,
 
 pub enum SurfaceContext {
 	#[cfg(target_family = "unix")]
-	Xcb(Arc<xcb::Connection>, xcb::Pixmap)
+	Xcb(Arc<xcb::Connection>, xcb::Pixmap),
+	Headless
 }
 
 #[cfg(target_family = "unix")]
@@ -80,7 +105,7 @@ impl Drop for SurfaceContext {
 /// Get cairo xcb (pixmap) surface from a `Connection`
 #[cfg(target_family = "unix")]
 fn xcb_pixmap_surface(connect: &crate::Connection,
-		   token: &Token, width: i32, height: i32) -> Option<(Surface, SurfaceContext)>
+		   token: &Token, width: i32, height: i32) -> Option<(XCBSurface, SurfaceContext)>
 {
 	use crate::{data::XcbData, stat::XcbStat::*};
 
@@ -122,10 +147,13 @@ pub fn png_surface<P>(path: P) -> Option<ImageSurface>
 }
 
 #[cfg(feature = "render")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(commands = cx.commands().len())))]
 pub(crate) fn render(cx: &context::Context, cr: Option<cairo::Context>, surface: &Surface) -> cairo::Context
 {
 	use context::{Command, ImageType, ImageFormat};
 
+	#[cfg(feature = "tracing")]
+	let start = std::time::Instant::now();
 	let mut cr = match cr {
 		None => cairo::Context::new(&surface),
 		Some(cr) => cr
@@ -211,5 +239,7 @@ pub(crate) fn render(cx: &context::Context, cr: Option<cairo::Context>, surface:
 		}
 	}
 	
+	#[cfg(feature = "tracing")]
+	tracing::debug!(elapsed = ?start.elapsed(), "rendered");
 	cr
 }
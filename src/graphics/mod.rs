@@ -1,6 +1,6 @@
 //! Graphics rendering api
 
-use std::{fmt, sync::Arc};
+use std::{fmt, sync::Arc, convert::TryFrom};
 use crate::{Token, Connection};
 
 #[cfg(all(feature = "cairo", feature = "render"))]
@@ -48,7 +48,8 @@ fn clear_surface(surface: &cairo::Surface) {
 
 enum SurfaceType {
 	Window(cairo::Surface),
-	Buffer(cairo::Surface, cairo::SurfaceContext)
+	Buffer(cairo::Surface, cairo::SurfaceContext),
+	Headless(cairo::Surface, crate::data::HeadlessSurface)
 }
 
 /// A surface for drawing graphics
@@ -57,6 +58,10 @@ pub struct Surface(SurfaceType);
 impl Surface {
 	/// Get a window surface from a `Connection`
 	pub fn window(connect: &Connection, window: &Token, dimensions: (i32, i32)) -> Option<Self> {
+		if let crate::SystemType::Headless = connect.system() {
+			return cairo::headless_surface(connect, window, dimensions)
+				.map(|(s, w)| Surface{ 0: SurfaceType::Headless(s, w) });
+		}
 		cairo::surface(connect, window, dimensions).map(|s| Surface{ 0: SurfaceType::Window(s) })
 	}
 
@@ -92,16 +97,18 @@ impl Surface {
 	pub fn clear(&self) {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => { clear_surface(s); }
+			Window(s) | Buffer(s, _) | Headless(s, _) => { clear_surface(s); }
 		}
+		self.store();
 	}
 
 	/// Render graphics to surface using context
 	pub fn render(&self, cx: &Context) {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => { cairo::render(cx, None, s); }
+			Window(s) | Buffer(s, _) | Headless(s, _) => { cairo::render(cx, None, s); }
 		}
+		self.store();
 	}
 
 	/// Use to copy a buffer surface to a window or another buffer
@@ -109,7 +116,9 @@ impl Surface {
 		use SurfaceType::*;
 		match (&self.0, &other.0) {
 			(Window(s1), Buffer(s2, _)) |
+			(Headless(s1, _), Buffer(s2, _)) |
 			(Buffer(s1, _), Window(s2)) |
+			(Buffer(s1, _), Headless(s2, _)) |
 			(Buffer(s1, _), Buffer(s2, _)) => {
 				let cr = cairo::Context::new(s1);
 				cr.set_source_surface(s2, 0.0, 0.0);
@@ -117,6 +126,7 @@ impl Surface {
 			},
 			_ => ()
 		}
+		self.store();
 	}
 
 	/// Update the surface
@@ -124,18 +134,28 @@ impl Surface {
 		use SurfaceType::*;
 		match &self.0 {
 			Window(s) => {
-				s.set_size(width, height);
+				if let Ok(s) = cairo::XCBSurface::try_from(s.clone()) {
+					s.set_size(width, height);
+				}
 			},
+			Headless(..) => self.store(),
 			_ => ()
 		}
 	}
 
+	/// Copy the drawing of a headless window surface to the window
+	fn store(&self) {
+		if let SurfaceType::Headless(s, window) = &self.0 {
+			window.copy_from(s);
+		}
+	}
+
 	/// Get text extent
 	pub fn text_extent<T>(&self, text: T, cx: Option<&Context>) -> TextExtent
 		where T: AsRef<str> {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => {
+			Window(s) | Buffer(s, _) | Headless(s, _) => {
 				let mut cr = cairo::Context::new(s);
 				if let Some(cx) = cx {
 					cr = cairo::render(cx, Some(cr), s);
@@ -150,7 +170,7 @@ impl Surface {
 	pub fn is_window_surface(&self) -> bool {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) => true,
+			Window(_) | Headless(..) => true,
 			_ => false
 		}
 	}
@@ -158,7 +178,7 @@ impl Surface {
 	fn inner_surface(&self) -> &cairo::Surface {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => s
+			Window(s) | Buffer(s, _) | Headless(s, _) => s
 		}
 	}
 }
@@ -170,7 +190,7 @@ impl Surface {
 	pub fn create_cairo_context(&self) -> cairo::Context {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => cairo::Context::new(s)
+			Window(s) | Buffer(s, _) | Headless(s, _) => cairo::Context::new(s)
 		}
 	}
 
@@ -179,7 +199,7 @@ impl Surface {
 	pub fn as_cairo_surface(&self) -> &cairo::Surface {
 		use SurfaceType::*;
 		match &self.0 {
-			Window(s) | Buffer(s, _) => &s
+			Window(s) | Buffer(s, _) | Headless(s, _) => &s
 		}
 	}
 }
@@ -0,0 +1,99 @@
+//! Raw window handles for use with other graphics crates
+
+use std::sync::Arc;
+use raw_window_handle::{
+	HasRawWindowHandle, HasRawDisplayHandle, RawWindowHandle, RawDisplayHandle,
+	XcbWindowHandle, XcbDisplayHandle
+};
+use crate::{Token, Connection, Body, Data, Error, data::XcbData, stat::XcbStat};
+
+/// The raw window and display handle of a session window.
+/// The handle keeps the connection to the system open,
+/// the window is only valid until the session ends.
+/// # Example
+/// ```no_run
+/// let mut connect = ren::Connection::open().unwrap();
+/// let session = connect.begin();
+///
+/// let handle = ren::WindowHandle::new(&connect, &session).unwrap();
+/// // Pass `&handle` to a crate that takes a `HasRawWindowHandle`
+/// ```
+#[derive(Clone)]
+pub struct WindowHandle {
+	connection: Arc<xcb::Connection>,
+	window: xcb::Window,
+	visual: xcb::Visualid,
+	screen: i32
+}
+
+impl WindowHandle {
+	/// Get the handle of a session window. Only windows of
+	/// `SystemType::Xcb` have a handle, others fail with `Error::Type`.
+	pub fn new(connect: &Connection, token: &Token) -> Result<Self, Error> {
+		let stat = |stat: XcbStat| match connect.request(token, stat)?.take_body() {
+			Body::Data(Data::Xcb(data)) => Ok(data),
+			_ => Err(Error::Type)
+		};
+
+		let connection = match stat(XcbStat::Connection)? {
+			XcbData::Connection(connection) => connection,
+			_ => return Err(Error::Type)
+		};
+		let window = match stat(XcbStat::Window)? {
+			XcbData::Window(window) => window,
+			_ => return Err(Error::Type)
+		};
+		let screen = match stat(XcbStat::Screen)? {
+			XcbData::Screen(screen) => screen,
+			_ => return Err(Error::Type)
+		};
+		// The visual is optional in the handle
+		let visual = match stat(XcbStat::VisualType) {
+			Ok(XcbData::VisualType(visual)) => visual.visual_id(),
+			_ => 0
+		};
+
+		Ok(Self {
+			connection,
+			window,
+			visual,
+			screen
+		})
+	}
+
+	/// Get the id of the window
+	pub fn window(&self) -> u32 {
+		self.window
+	}
+}
+
+unsafe impl HasRawWindowHandle for WindowHandle {
+	fn raw_window_handle(&self) -> RawWindowHandle {
+		let mut handle = XcbWindowHandle::empty();
+		handle.window = self.window;
+		handle.visual_id = self.visual;
+		RawWindowHandle::Xcb(handle)
+	}
+}
+
+unsafe impl HasRawDisplayHandle for WindowHandle {
+	fn raw_display_handle(&self) -> RawDisplayHandle {
+		let mut handle = XcbDisplayHandle::empty();
+		handle.connection = self.connection.get_raw_conn().cast();
+		handle.screen = self.screen;
+		RawDisplayHandle::Xcb(handle)
+	}
+}
+
+impl std::fmt::Debug for WindowHandle {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("WindowHandle")
+			.field("window", &self.window)
+			.field("visual", &self.visual)
+			.field("screen", &self.screen)
+			.finish()
+	}
+}
+
+unsafe impl Send for WindowHandle {}
+unsafe impl Sync for WindowHandle {}
@@ -0,0 +1,48 @@
+use crate::{Token, Event, Message, Error};
+
+/// An `Interceptor` sees every `Message` sent to the sessions of a
+/// `Connection` and every `Event` they deliver. It can log, replace,
+/// drop or veto them, and inject events of its own. Interceptors are
+/// added with `Connection::intercept` and run in the order they were added.
+/// # Example
+/// ```
+/// use ren::{SystemType, Token, Message, Body, Error, Interceptor, WindowCommand::*};
+///
+/// /// Drops every `Update`
+/// struct NoUpdate;
+///
+/// impl Interceptor for NoUpdate {
+///     fn message(&self, _: &Token, message: Message) -> Result<Option<Message>, Error> {
+///         match message.body() {
+///             Body::Command(ren::Command::Window(Update)) => Ok(None),
+///             _ => Ok(Some(message))
+///         }
+///     }
+/// }
+///
+/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+/// connect.intercept(NoUpdate);
+///
+/// let session = connect.begin();
+/// assert_eq!(connect.request(&session, Update), Ok(Message::empty()));
+/// ```
+pub trait Interceptor: Send + Sync {
+	/// Called with each message before it is handled. Return `None`
+	/// to drop the message or an error to fail the request with it.
+	fn message(&self, _: &Token, message: Message) -> Result<Option<Message>, Error> {
+		Ok(Some(message))
+	}
+
+	/// Called with each event before it is delivered.
+	/// Return `None` to drop the event.
+	fn event(&self, _: &Token, event: Event) -> Option<Event> {
+		Some(event)
+	}
+
+	/// Called each time an event of a session is taken, by `.wait/.poll`,
+	/// `.wait_any/.poll_any`, `.drain` or a stream. An event returned
+	/// is delivered before the events of the session.
+	fn inject(&self, _: &Token) -> Option<Event> {
+		None
+	}
+}
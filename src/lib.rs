@@ -5,7 +5,7 @@
 //!
 //! # Basic example
 //! Display a small window on screen
-//! ```
+//! ```no_run
 //! use ren::WindowCommand::*;
 //!
 //! // Open a connection
@@ -31,17 +31,42 @@
 //!     println!("{:?}", event);
 //!
 //!     match event {
-//!         // Terminate application
-//!         ren::Event::Terminate => break,
+//!         // Close application
+//!         ren::Event::CloseRequested => break,
 //!         _ => ()
 //!     }
 //! }
 //! ```
+//!
+//! # Headless
+//! Without a display server, such as in tests or CI,
+//! open a connection to the in-memory `SystemType::Headless`
+//! ```
+//! use ren::{SystemType, WindowCommand::*};
+//!
+//! let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+//! let session = connect.begin();
+//!
+//! connect.requests(&session, &[Dimension((320, 240)), Map]);
+//!
+//! let event = connect.wait(&session).unwrap();
+//! assert_eq!(event, ren::DisplayEvent::Resize((320, 240)).into());
+//! ```
 
 mod context;
 mod system;
 mod session;
+mod timer;
 mod connection;
+mod attributes;
+mod proxy;
+mod intercept;
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+mod stream;
+#[cfg(all(feature = "raw-window-handle", target_family = "unix"))]
+mod handle;
+#[cfg(feature = "record")]
+mod record;
 
 pub mod event;
 #[cfg(any(feature = "render", feature = "cairo"))]
@@ -53,6 +78,19 @@ pub mod message;
 mod prelude;
 pub use prelude::*;
 pub use connection::Connection;
+pub use attributes::WindowAttributes;
+pub use proxy::EventProxy;
+pub use intercept::Interceptor;
+#[cfg(all(feature = "raw-window-handle", target_family = "unix"))]
+pub use handle::WindowHandle;
+#[cfg(feature = "raw-window-handle")]
+pub use raw_window_handle;
 
 #[cfg(feature = "async-rt")]
 pub use async_std;
+#[cfg(feature = "tokio-rt")]
+pub use tokio;
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+pub use futures_core;
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+pub use stream::EventStream;
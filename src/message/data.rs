@@ -4,14 +4,25 @@ extern crate xcb;
 use std::{fmt::{Debug, Formatter, Error}, sync::Arc};
 use super::Body;
 
+#[cfg(any(feature = "render", feature = "cairo"))]
+pub use crate::context::headless::HeadlessSurface;
+
 /// A type containing status data
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Data {
     /// Window data
     Window(WindowData),
     /// When using XCB
-    Xcb(XcbData)
+    Xcb(XcbData),
+    /// When using the mock system
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Mock(MockData),
+    /// When using the headless system
+    #[cfg(any(feature = "render", feature = "cairo"))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Headless(HeadlessData)
 }
 
 impl From<Data> for Body {
@@ -35,13 +46,22 @@ macro_rules! data_from {
 /// Window status data
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowData {
     /// Get the window position
     Position((i32, i32)),
     /// Get the window dimensions
     Dimension((u32, u32)),
     /// Get the window depth
-    Depth(u8)
+    Depth(u8),
+    /// Get the window title
+    Title(String),
+    /// Get whether the window is mapped
+    Mapped(bool),
+    /// Get the id of the parent window, `None` for a top-level window
+    Parent(Option<u32>),
+    /// Get the position of the window among its siblings, from the bottom
+    Stacking(usize)
 }
 
 data_from!(WindowData, Window);
@@ -50,13 +70,18 @@ body_from!(WindowData, Data);
 /// XCB status data
 #[non_exhaustive]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XcbData {
     /// Get the connection
+    #[cfg_attr(feature = "serde", serde(skip))]
     Connection(Arc<xcb::Connection>),
     /// Get the window ID
     Window(xcb::Window),
     /// Get the visualtype
+    #[cfg_attr(feature = "serde", serde(skip))]
     VisualType(xcb::Visualtype),
+    /// Get the screen number
+    Screen(i32),
     /// Pixmap
     Pixmap(xcb::Pixmap)
 }
@@ -68,6 +93,7 @@ impl PartialEq for XcbData {
             (Connection(a), Connection(b)) => a.get_raw_conn() == b.get_raw_conn(),
             (Window(a), Window(b)) => a == b,
             (Pixmap(a), Pixmap(b)) => a == b,
+            (Screen(a), Screen(b)) => a == b,
             _ => false
         }
     }
@@ -80,6 +106,7 @@ impl Debug for XcbData {
             Connection(c) => c.get_raw_conn().fmt(f),
             Window(w) => w.fmt(f),
             VisualType(_) => write!(f, "Visualtype"),
+            Screen(s) => s.fmt(f),
             Pixmap(p) => p.fmt(f)
         }
     }
@@ -90,3 +117,28 @@ unsafe impl Sync for XcbData {}
 
 data_from!(XcbData, Xcb);
 body_from!(XcbData, Data);
+
+/// Mock status data
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum MockData {
+    /// Get the mock handle
+    Handle(crate::Mock)
+}
+
+data_from!(MockData, Mock);
+body_from!(MockData, Data);
+
+/// Headless status data
+#[cfg(any(feature = "render", feature = "cairo"))]
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub enum HeadlessData {
+    /// Get the image surface
+    Surface(HeadlessSurface)
+}
+
+#[cfg(any(feature = "render", feature = "cairo"))]
+data_from!(HeadlessData, Headless);
+#[cfg(any(feature = "render", feature = "cairo"))]
+body_from!(HeadlessData, Data);
@@ -21,10 +21,11 @@ pub mod stat;
 use stat::Stat;
 use data::Data;
 use uuid::Uuid;
-use crate::event::Event;
+use crate::event::{Event, EventSet};
 
 /// The type of the `Message`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     /// An empty message
     Empty,
@@ -39,9 +40,11 @@ pub enum Type {
 }
 
 /// The body of the message
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Body {
     /// Has no body
+    #[default]
     None,
     /// Status
     Stat(Stat),
@@ -55,20 +58,16 @@ pub enum Body {
     Custom(String)
 }
 
-impl Default for Body {
-    fn default() -> Self
-    {
-        Body::None
-    }
-}
-
 /// A `Message` command. Found within the
 /// body of the message.
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
     /// A window command
-    Window(WindowCommand)
+    Window(WindowCommand),
+    /// A command when using XCB
+    Xcb(XcbCommand)
 }
 
 impl From<Command> for Body {
@@ -88,6 +87,7 @@ impl From<Event> for Body {
 /// Commands for window requests.
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowCommand {
     /// Request window title
     Title(String),
@@ -106,7 +106,38 @@ pub enum WindowCommand {
     /// Request to clear the window
     Clear,
     /// Request to update the window
-    Update
+    Update,
+    /// Request to close and destroy the window, ending the session
+    Close,
+    /// Request to receive only the events of the categories in the set
+    Subscribe(EventSet)
+}
+
+/// Commands when using XCB
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum XcbCommand {
+    /// Embed the foreign client window with this id into the
+    /// session window, following the XEmbed protocol
+    Embed(u32),
+    /// Tell the embedded client with this id that
+    /// the session window became active
+    Activate(u32),
+    /// Tell the embedded client with this id that
+    /// the session window became inactive
+    Deactivate(u32),
+    /// Give the focus to the embedded client with this id
+    FocusIn(u32),
+    /// Take the focus from the embedded client with this id
+    FocusOut(u32)
+}
+
+impl From<XcbCommand> for Body {
+    fn from(x: XcbCommand) -> Self
+    {
+        Body::Command(Command::Xcb(x))
+    }
 }
 
 impl From<WindowCommand> for Body {
@@ -127,6 +158,17 @@ pub enum Error {
     Session,
     /// No event occurred
     NoEvent,
+    /// The session window was closed or the connection was lost
+    Closed,
+    /// The windowing system rejected a checked request
+    Protocol {
+        /// error code
+        code: u8,
+        /// major opcode of the failed request
+        major: u8,
+        /// id of the resource the request was for
+        resource: u32
+    },
     /// Custom error message
     Custom(String)
 }
@@ -140,6 +182,10 @@ impl From<&Error> for String {
             Token => "supplied an undefined token".into(),
             Session => "session error".into(),
             NoEvent => "no event".into(),
+            Closed => "the session was closed".into(),
+            Protocol { code, major, resource } => {
+                format!("error {} for request {} on resource {:#x}", code, major, resource)
+            },
             Custom(s) => s.into()
         }
     }
@@ -170,13 +216,38 @@ impl Token {
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for Token {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer
+    {
+        serializer.collect_str(&self.uuid)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Token {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where D: serde::Deserializer<'de>
+    {
+        let uuid = String::deserialize(deserializer)?;
+        Uuid::parse_str(&uuid)
+            .map(|uuid| Self { uuid })
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 /// A `Message` for bi-directional communication
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Message {
     /// message type
     ty: Type,
     /// message body
-    body: Body
+    body: Body,
+    /// id of an asynchronous request and its response
+    #[cfg_attr(feature = "serde", serde(default))]
+    id: Option<Token>
 }
 
 impl Message {
@@ -186,7 +257,8 @@ impl Message {
     {
         Self {
             ty: ty.into(),
-            body: body.into()
+            body: body.into(),
+            id: None
         }
     }
 
@@ -196,7 +268,8 @@ impl Message {
     {
         Self {
             ty: Type::Response,
-            body: body.into()
+            body: body.into(),
+            id: None
         }
     }
 
@@ -206,7 +279,8 @@ impl Message {
     {
         Self {
             ty: Type::Request,
-            body: body.into()
+            body: body.into(),
+            id: None
         }
     }
 
@@ -234,21 +308,32 @@ impl Message {
         std::mem::take(&mut self.body)
     }
 
+    /// Get the id of the message. A request sent with
+    /// `Connection::send_async` and its response share an id.
+    pub fn id(&self) -> Option<&Token>
+    {
+        self.id.as_ref()
+    }
+
+    pub(crate) fn with_id(mut self, id: Option<Token>) -> Self
+    {
+        self.id = id;
+        self
+    }
+
     /// If the message is empty
     pub fn is_empty(&self) -> bool
     {
-        match &self.ty {
-            Type::Empty => true,
-            _ => false
-        }
+        matches!(self.ty, Type::Empty)
     }
 }
 
 /// The message queue
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct MessageQueue {
     messages: Vec<Message>,
-    limit: Option<usize>
+    limit: Option<usize>,
+    validate: bool
 }
 
 impl MessageQueue {
@@ -257,7 +342,8 @@ impl MessageQueue {
     {
         Self {
             messages: Vec::new(),
-            limit: None
+            limit: None,
+            validate: false
         }
     }
 
@@ -290,6 +376,13 @@ impl MessageQueue {
         self.limit = limit;
     }
 
+    /// Check every message before any is sent when the queue
+    /// is dispatched, an invalid message cancels the whole queue
+    pub fn validate(&mut self, validate: bool)
+    {
+        self.validate = validate;
+    }
+
     /// Get the number of messages in the queue
     #[inline]
     pub fn size(&self) -> usize
@@ -312,6 +405,12 @@ impl MessageQueue {
         &self.messages
     }
 
+    pub(crate) fn validates(&self) -> bool
+    {
+        self.validate
+    }
+
+    #[allow(dead_code)]
     pub(crate) fn join(&mut self, other: &mut Self)
     {
         self.messages.append(&mut other.messages);
@@ -4,11 +4,17 @@ use super::Body;
 /// A type used to get status info
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Stat {
     /// Window status
     Window(WindowStat),
     /// When using XCB
-    Xcb(XcbStat)
+    Xcb(XcbStat),
+    /// When using the mock system
+    Mock(MockStat),
+    /// When using the headless system
+    #[cfg(any(feature = "render", feature = "cairo"))]
+    Headless(HeadlessStat)
 }
 
 impl From<Stat> for Body {
@@ -32,13 +38,22 @@ macro_rules! stat_from {
 /// Window status info
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowStat {
     /// Get the window position
     Position,
     /// Get the window dimensions
     Dimension,
     /// Get the window depth
-    Depth
+    Depth,
+    /// Get the window title
+    Title,
+    /// Get whether the window is mapped
+    Mapped,
+    /// Get the id of the parent window
+    Parent,
+    /// Get the position of the window among its siblings, from the bottom
+    Stacking
 }
 
 stat_from!(WindowStat, Window);
@@ -47,6 +62,7 @@ body_from!(WindowStat, Stat);
 /// XCB status info
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum XcbStat {
     /// Get the raw connection
     Connection,
@@ -54,9 +70,38 @@ pub enum XcbStat {
     Window,
     /// Get the Visualtype
     VisualType,
+    /// Get the screen number
+    Screen,
     /// Create a new Pixmap with `width` and `height`
     Pixmap(u16, u16)
 }
 
 stat_from!(XcbStat, Xcb);
 body_from!(XcbStat, Stat);
+
+/// Mock status info
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MockStat {
+    /// Get the mock handle of the session
+    Handle
+}
+
+stat_from!(MockStat, Mock);
+body_from!(MockStat, Stat);
+
+/// Headless status info
+#[cfg(any(feature = "render", feature = "cairo"))]
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HeadlessStat {
+    /// Get the image surface the window renders into
+    Surface
+}
+
+#[cfg(any(feature = "render", feature = "cairo"))]
+stat_from!(HeadlessStat, Headless);
+#[cfg(any(feature = "render", feature = "cairo"))]
+body_from!(HeadlessStat, Stat);
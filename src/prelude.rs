@@ -1,10 +1,10 @@
 
 pub use crate::{
-	context::ConnectionError,
+	context::{ConnectionError, mock::Mock},
     event::{Event, InputEvent, DisplayEvent, input::{KeyEvent, MouseEvent}},
     message::{
         Error, Token, Message, MessageQueue, Status, Type, Body,
-        Command, WindowCommand, data, data::Data, stat, stat::Stat
+        Command, WindowCommand, XcbCommand, data, data::Data, stat, stat::Stat
     },
     system::SystemType
 };
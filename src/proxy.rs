@@ -0,0 +1,47 @@
+use crate::{Event, Message, Status, Error, system::Proxy};
+
+/// A handle used to post `Event::User` events to a session
+/// from any thread. A blocked `.wait` on the session returns
+/// as soon as the event is posted.
+/// # Example
+/// ```
+/// use ren::SystemType;
+///
+/// let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+/// let session = connect.begin();
+/// let proxy = connect.proxy(&session).unwrap();
+///
+/// std::thread::spawn(move || {
+///     proxy.post("data arrived").unwrap();
+/// }).join().unwrap();
+///
+/// assert_eq!(connect.wait(&session), Ok(ren::Event::User("data arrived".into())));
+/// ```
+#[derive(Clone)]
+pub struct EventProxy {
+	proxy: Proxy
+}
+
+impl EventProxy {
+	pub(crate) fn new(proxy: Proxy) -> Self {
+		Self {
+			proxy
+		}
+	}
+
+	/// Post a user payload to the session.
+	/// Fails with `Error::Token` once the session has ended.
+	pub fn post<S>(&self, payload: S) -> Status
+		where S: Into<String> {
+		match self.proxy.post(Event::User(payload.into())) {
+			false => Err(Error::Token),
+			true => Ok(Message::empty())
+		}
+	}
+}
+
+impl std::fmt::Debug for EventProxy {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+		write!(f, "EventProxy")
+	}
+}
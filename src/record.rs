@@ -0,0 +1,100 @@
+use std::{
+	collections::VecDeque,
+	fs::File,
+	io::{BufRead, BufReader, BufWriter, Write},
+	path::Path,
+	thread,
+	time::{Duration, Instant}
+};
+use crate::{Event, Error};
+
+/// An event and the time it was received, in microseconds
+/// since the recording started. Written as one JSON line.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Record {
+	time: u64,
+	event: Event
+}
+
+fn io_error(e: impl std::fmt::Display) -> Error {
+	Error::Custom(e.to_string())
+}
+
+/// Writes the events of a session to a file
+pub struct Recorder {
+	file: BufWriter<File>,
+	start: Instant
+}
+
+impl Recorder {
+	/// Start a recording, replacing the file at `path`
+	pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		Ok(Self {
+			file: BufWriter::new(File::create(path).map_err(io_error)?),
+			start: Instant::now()
+		})
+	}
+
+	/// Append an event to the recording. Each event is flushed
+	/// so the recording survives a crash of the application.
+	pub fn write(&mut self, event: &Event) -> Result<(), Error> {
+		let record = Record {
+			time: self.start.elapsed().as_micros() as u64,
+			event: event.clone()
+		};
+		serde_json::to_writer(&mut self.file, &record).map_err(io_error)?;
+		self.file.write_all(b"\n").map_err(io_error)?;
+		self.file.flush().map_err(io_error)
+	}
+}
+
+/// Feeds the events of a recording back to a session
+pub struct Replay {
+	events: VecDeque<(Duration, Event)>,
+	start: Instant
+}
+
+impl Replay {
+	/// Read a recording. The delays between events are divided by `speed`.
+	pub fn open<P: AsRef<Path>>(path: P, speed: f64) -> Result<Self, Error> {
+		if speed.is_nan() || speed <= 0.0 {
+			return Err(Error::Custom("the replay speed must be positive".into()));
+		}
+		let file = BufReader::new(File::open(path).map_err(io_error)?);
+		let mut events = VecDeque::new();
+		// The delays are measured from the first event, it is due at once
+		let mut first = None;
+		for line in file.lines() {
+			let line = line.map_err(io_error)?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			let record: Record = serde_json::from_str(&line).map_err(io_error)?;
+			let first = *first.get_or_insert(record.time);
+			let time = Duration::from_micros(record.time.saturating_sub(first)).div_f64(speed);
+			events.push_back((time, record.event));
+		}
+		Ok(Self {
+			events,
+			start: Instant::now()
+		})
+	}
+
+	/// Check if every event was replayed
+	pub fn finished(&self) -> bool {
+		self.events.is_empty()
+	}
+
+	/// Take the next event once it is due, waiting for it with `block`
+	pub fn next(&mut self, block: bool) -> Option<Event> {
+		let due = self.start + self.events.front()?.0;
+		let now = Instant::now();
+		if due > now {
+			if !block {
+				return None;
+			}
+			thread::sleep(due - now);
+		}
+		self.events.pop_front().map(|(_, event)| event)
+	}
+}
@@ -20,12 +20,15 @@ use std::{path::{Path, PathBuf}, sync::Arc};
 use super::shape::{Point, Rect};
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImageType {
 	Path(PathBuf),
 	Data(Vec<u8>, ImageFormat, u32, u32),
 	#[cfg(feature = "render")]
+	#[cfg_attr(feature = "serde", serde(skip))]
 	Surface(Arc<crate::graphics::Surface>),
 	#[cfg(feature = "render")]
+	#[cfg_attr(feature = "serde", serde(skip))]
 	ImageSurface(crate::graphics::ImageSurface)
 }
 
@@ -44,6 +47,7 @@ impl PartialEq for ImageType {
 
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ImageFormat {
 	/// Pixel is 24-bits, 8-bits per component
 	Rgb8,
@@ -58,6 +62,7 @@ pub enum ImageFormat {
 /// Drawing command operations
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
 	Rgb(f64, f64, f64),
 	Rgba(f64, f64, f64, f64),
@@ -85,6 +90,7 @@ pub enum Command {
 
 /// Context used for drawing operations
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Context {
 	commands: Vec<Command>
 }
@@ -5,6 +5,7 @@ use std::ops::Add;
 /// A Point is a simple object that
 /// represents a single location
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Eq, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     /// The x coordinate
     pub x: isize,
@@ -47,6 +48,7 @@ impl From<&Point> for (isize, isize) {
 /// A rectangular area with an origin (Point),
 /// as well as a width and height
 #[derive(Debug, Clone, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     /// The origin of the rectangle
     pub point: Point,
@@ -1,13 +1,36 @@
 
 use crate::{
-	Token, Event, Message, Command, Status, Body, Error, Type,
-	MessageQueue, system::Window
+	Token, Event, Message, Command, WindowCommand, Status, Body, Data, Error, Type,
+	MessageQueue, system::{Window, Proxy, Pending}, timer::Timers
 };
-use std::collections::HashMap;
+#[cfg(feature = "record")]
+use crate::record::{Recorder, Replay};
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+use {std::sync::Arc, crate::stream::Parked};
+use std::{
+	collections::{HashMap, VecDeque},
+	sync::{Mutex, MutexGuard, atomic::{AtomicBool, Ordering}},
+	time::Instant
+};
+
+/// Passes a message of a batch through the interceptors of the connection
+pub type Intercept<'a> = dyn Fn(Message) -> Result<Option<Message>, Error> + 'a;
 
 /// A single window session
 pub struct Session {
 	window: Window,
+	timers: Mutex<Timers>,
+	checked: bool,
+	closed: AtomicBool,
+	/// Requests sent without waiting, in the order they were sent
+	replies: Mutex<VecDeque<(Token, Reply)>>,
+	#[cfg(feature = "record")]
+	recorder: Mutex<Option<Recorder>>,
+	#[cfg(feature = "record")]
+	replay: Mutex<Option<Replay>>,
+	/// Streams waiting for the session to be released
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	parked: Arc<Parked>,
 	pub batch: HashMap<Token, MessageQueue>
 }
 
@@ -15,62 +38,351 @@ impl Session {
 	pub fn new(window: Window) -> Self {
 		Self {
 			window,
+			timers: Mutex::new(Timers::default()),
+			checked: false,
+			closed: AtomicBool::new(false),
+			replies: Mutex::new(VecDeque::new()),
+			#[cfg(feature = "record")]
+			recorder: Mutex::new(None),
+			#[cfg(feature = "record")]
+			replay: Mutex::new(None),
+			#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+			parked: Arc::default(),
 			batch: HashMap::new()
 		}
 	}
 
+	pub fn timers(&self) -> MutexGuard<'_, Timers> {
+		self.timers.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn expire(&self) -> Option<Event> {
+		self.timers().expire().map(Event::Timer)
+	}
+
+	fn replies(&self) -> MutexGuard<'_, VecDeque<(Token, Reply)>> {
+		self.replies.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Send a stat request without waiting for the response, which is
+	/// kept under `id`. A message dropped by an interceptor is `None`,
+	/// its response is empty.
+	pub fn send_async(&self, id: Token, message: Option<&Message>) -> Result<(), Error> {
+		if self.closed() {
+			return Err(Error::Closed);
+		}
+		let pending = match message.map(|m| (m.ty(), m.body())) {
+			None => Pending::Ready(None),
+			Some((Type::Request, Body::Stat(stat))) => self.window.stat_async(*stat),
+			Some(_) => return Err(Error::Type)
+		};
+		self.replies().push_back((id, Reply::Pending(pending)));
+		Ok(())
+	}
+
+	/// Keep the `response` to a request that was handled when it was sent
+	pub fn respond(&self, id: Token, response: Message) -> Result<(), Error> {
+		if self.closed() {
+			return Err(Error::Closed);
+		}
+		self.replies().push_back((id, Reply::Ready(response.with_id(Some(id)))));
+		Ok(())
+	}
+
+	/// Take the response to the request `id`, waiting for it with `block`
+	pub fn reply(&self, id: &Token, block: bool) -> Status {
+		let mut replies = self.replies();
+		let index = replies.iter().position(|(i, _)| i == id).ok_or(Error::Token)?;
+		match replies[index].1.take(*id, block) {
+			None => Err(Error::NoEvent),
+			Some(response) => {
+				replies.remove(index);
+				Ok(response)
+			}
+		}
+	}
+
+	/// The response to the oldest request as an event. The system
+	/// answers requests in order, so a later one is never ready first.
+	fn replied(&self, block: bool) -> Option<Event> {
+		let mut replies = self.replies();
+		let (id, reply) = replies.front_mut()?;
+		let response = reply.take(*id, block)?;
+		replies.pop_front();
+		Some(Event::Reply(Box::new(response)))
+	}
+
+	/// Wait for the system to accept each command
+	pub fn set_checked(&mut self, checked: bool) {
+		self.checked = checked;
+	}
+
+	/// Check if the window was closed or the connection lost
+	pub fn closed(&self) -> bool {
+		self.closed.load(Ordering::Relaxed) || self.window.closed()
+	}
+
+	/// Mark the session closed once it delivers an event that ends it
+	pub fn track(&self, event: &Event) {
+		#[cfg(feature = "record")]
+		self.record(event);
+		if let Event::Terminate | Event::Disconnected = event {
+			self.closed.store(true, Ordering::Relaxed);
+		}
+	}
+
+	/// Record every delivered event, or stop recording with `None`
+	#[cfg(feature = "record")]
+	pub fn set_recorder(&self, recorder: Option<Recorder>) {
+		*self.recorder.lock().unwrap_or_else(|e| e.into_inner()) = recorder;
+	}
+
+	#[cfg(feature = "record")]
+	fn record(&self, event: &Event) {
+		let mut recorder = self.recorder.lock().unwrap_or_else(|e| e.into_inner());
+		// A recording that can not be written is stopped
+		if let Some(Err(_)) = recorder.as_mut().map(|r| r.write(event)) {
+			*recorder = None;
+		}
+	}
+
+	/// Deliver the events of `replay` before any other event
+	#[cfg(feature = "record")]
+	pub fn set_replay(&self, replay: Replay) {
+		let replay = Some(replay).filter(|r| !r.finished());
+		*self.replay.lock().unwrap_or_else(|e| e.into_inner()) = replay;
+	}
+
+	/// The next replayed event, `None` once there is no replay
+	#[cfg(feature = "record")]
+	fn replayed(&self, block: bool) -> Option<Result<Event, Error>> {
+		let mut replay = self.replay.lock().unwrap_or_else(|e| e.into_inner());
+		let event = replay.as_mut()?.next(block);
+		if replay.as_ref().is_some_and(Replay::finished) {
+			*replay = None;
+		}
+		Some(event.ok_or(Error::NoEvent))
+	}
+
+	pub fn window(&self) -> &Window {
+		&self.window
+	}
+
+	/// Move the window into the window of `parent`, or make it a top-level window
+	pub fn reparent(&self, parent: Option<&Session>, origin: (u32, u32)) -> Result<(), Error> {
+		if self.closed() || parent.is_some_and(|p| p.closed()) {
+			return Err(Error::Closed);
+		}
+		self.window.reparent(parent.map(|p| &p.window), origin)
+	}
+
+	/// The id of the session window in the system
+	pub fn id(&self) -> Option<u32> {
+		self.window.id()
+	}
+
+	pub fn proxy(&self) -> Proxy {
+		self.window.proxy()
+	}
+
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn parked(&self) -> &Arc<Parked> {
+		&self.parked
+	}
+
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn register_waker(&self, waker: &std::task::Waker) {
+		self.window.register_waker(waker);
+	}
+
 	pub fn wait(&self) -> Result<Event, Error> {
-		let event = self.window.event();
-		event.ok_or(Error::NoEvent)
+		let event = self.next();
+		if let Ok(event) = &event {
+			self.track(event);
+		}
+		event
+	}
+
+	fn next(&self) -> Result<Event, Error> {
+		#[cfg(feature = "record")]
+		if let Some(event) = self.replayed(true) {
+			return event;
+		}
+		if let Some(event) = self.replied(true) {
+			return Ok(event);
+		}
+		loop {
+			// Only queued events are left for a closed window
+			if self.closed() {
+				return self.ready().map_err(|_| Error::Closed);
+			}
+
+			if let Some(event) = self.expire() {
+				return Ok(event);
+			}
+
+			let deadline = match self.timers().deadline() {
+				None => return self.window.event().ok_or(Error::NoEvent),
+				Some(deadline) => deadline
+			};
+
+			let timeout = deadline.saturating_duration_since(Instant::now());
+			if let Some(event) = self.window.event_timeout(timeout) {
+				return Ok(event);
+			}
+		}
 	}
 
 	pub fn poll(&self) -> Result<Event, Error> {
-		let event = self.window.poll();
-		event.ok_or(Error::NoEvent)
+		let event = self.ready();
+		if let Ok(event) = &event {
+			self.track(event);
+		}
+		event
+	}
+
+	/// The next event that is ready, it is not tracked yet
+	fn ready(&self) -> Result<Event, Error> {
+		#[cfg(feature = "record")]
+		if let Some(event) = self.replayed(false) {
+			return event;
+		}
+		let event = self.replied(false)
+			.or_else(|| self.expire())
+			.or_else(|| self.window.poll());
+		match event {
+			Some(event) => Ok(event),
+			None if self.closed() => Err(Error::Closed),
+			None => Err(Error::NoEvent)
+		}
 	}
 
-	fn command(&mut self, command: &Command) {
+	fn command(&mut self, command: &Command) -> Result<(), Error> {
 		match command {
+			Command::Window(command) if self.checked => {
+				self.window.window_checked(command)
+			},
 			Command::Window(command) => {
 				self.window.window(command);
-			}
+				Ok(())
+			},
+			Command::Xcb(command) => self.window.xcb(self.checked, command)
 		}
 	}
 
 	fn body(&mut self, body: &Body) -> Status {
 		match body {
+			Body::Stat(s) if self.checked => {
+				if let Some(data) = self.window.stat_checked(*s)? {
+					return Ok(Message::response(data));
+				}
+			},
 			Body::Stat(s) => {
-				match self.window.stat(*s) {
-					Some(data) => return Ok(Message::response(data)),
-					_ => ()
+				if let Some(data) = self.window.stat(*s) {
+					return Ok(Message::response(data));
 				}
 			},
-			Body::Command(c) => { self.command(c); },
+			Body::Command(c) => self.command(c)?,
 			_ => ()
 		}
 		Ok(Message::empty())
 	}
 
+	#[cfg_attr(feature = "tracing", tracing::instrument(
+		level = "trace",
+		skip_all,
+		fields(ty = ?message.ty(), body = body_kind(message.body()))
+	))]
 	pub fn handle(&mut self, message: &Message) -> Status {
 		use Type::*;
+		if self.closed() {
+			return Err(Error::Closed);
+		}
 		match message.ty() {
-			Request => self.body(&message.body()),
+			Request => self.body(message.body()).map(|r| r.with_id(message.id().copied())),
 			_ => Err(Error::Type)
 		}
 	}
 
-	pub fn run(&mut self, token: &Token) -> Status {
-		let queue = match self.batch.remove(token) {
-			None => return Err(Error::Token),
-			Some(queue) => queue
-		};
+	/// Check a message without sending it. A custom type
+	/// is valid if `handled` has a handler for it.
+	fn validate(&self, message: &Message, handled: &dyn Fn(&str) -> bool) -> Result<(), Error> {
+		match (message.ty(), message.body()) {
+			(Type::Request, Body::Command(Command::Window(command))) => self.window.validate(command),
+			(Type::Request, _) => Ok(()),
+			(Type::Custom(ty), _) if handled(ty) => Ok(()),
+			_ => Err(Error::Type)
+		}
+	}
 
-		for message in queue.messages() {
-			self.handle(message)?;
+	/// Get the messages of a batch ready to send. Each message first
+	/// passes `intercept`, which can drop it or fail the batch. A queue
+	/// that validates is checked as a whole. A failed batch sends nothing.
+	pub fn prepare(&self, token: &Token, intercept: &Intercept,
+		handled: &dyn Fn(&str) -> bool) -> Result<Vec<Option<Message>>, Error> {
+		if self.closed() {
+			return Err(Error::Closed);
+		}
+		let queue = self.batch.get(token).ok_or(Error::Token)?;
+		let messages = queue.messages()
+			.iter()
+			.map(|message| intercept(message.clone()))
+			.collect::<Result<Vec<_>, _>>()?;
+		if queue.validates() {
+			for message in messages.iter().flatten() {
+				self.validate(message, handled)?;
+			}
 		}
+		Ok(messages)
+	}
 
-		self.batch.insert(token.clone(), queue);
+	/// Send a message of a batch. `Update` commands are
+	/// left to the flush after the last message.
+	pub fn step(&mut self, message: &Message) -> Status {
+		match (message.ty(), message.body()) {
+			(Type::Request, Body::Command(Command::Window(WindowCommand::Update))) => {
+				Ok(Message::empty())
+			},
+			_ => self.handle(message)
+		}
+	}
+}
 
-		Ok(Message::empty())
+/// The response to a request sent without waiting for it
+enum Reply {
+	/// A stat the system has yet to answer
+	Pending(Pending),
+	/// A message handled when it was sent
+	Ready(Message)
+}
+
+impl Reply {
+	/// Take the response to the request `id` if it arrived, waiting for it with `block`
+	fn take(&mut self, id: Token, block: bool) -> Option<Message> {
+		match self {
+			Reply::Pending(pending) if block => Some(response(id, pending.wait())),
+			Reply::Pending(pending) => pending.poll().map(|data| response(id, data)),
+			Reply::Ready(message) => Some(std::mem::replace(message, Message::empty()))
+		}
+	}
+}
+
+/// The response to the request `id`, empty if the system had no data
+fn response(id: Token, data: Option<Data>) -> Message {
+	data.map(Message::response)
+		.unwrap_or_else(Message::empty)
+		.with_id(Some(id))
+}
+
+/// The kind of a message body, without its contents
+#[cfg(feature = "tracing")]
+fn body_kind(body: &Body) -> &'static str {
+	match body {
+		Body::None => "none",
+		Body::Stat(_) => "stat",
+		Body::Data(_) => "data",
+		Body::Command(_) => "command",
+		Body::Event(_) => "event",
+		Body::Custom(_) => "custom"
 	}
 }
@@ -0,0 +1,284 @@
+use std::{
+	io,
+	future::Future,
+	pin::Pin,
+	sync::{Arc, Mutex, RwLock},
+	task::{Context, Poll, Waker},
+	time::Instant,
+	os::unix::io::RawFd
+};
+use futures_core::Stream;
+use crate::{Token, Event, Error, Connection, session::Session};
+
+/// The connection file descriptor, owned by the system
+struct Fd(RawFd);
+
+/// Completes once the connection is readable
+type Readable = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+/// Readiness of the connection using the async-std reactor
+#[cfg(feature = "async-rt")]
+#[derive(Clone)]
+struct Source(Arc<async_io::Async<Fd>>);
+
+#[cfg(feature = "async-rt")]
+impl std::os::unix::io::AsFd for Fd {
+	fn as_fd(&self) -> std::os::unix::io::BorrowedFd<'_> {
+		unsafe { std::os::unix::io::BorrowedFd::borrow_raw(self.0) }
+	}
+}
+
+#[cfg(feature = "async-rt")]
+impl Source {
+	fn new(fd: RawFd) -> io::Result<Self> {
+		async_io::Async::new_nonblocking(Fd(fd)).map(|fd| Source(Arc::new(fd)))
+	}
+
+	fn readable(&self) -> Readable {
+		Box::pin(Arc::clone(&self.0).readable_owned())
+	}
+}
+
+/// Readiness of the connection using the tokio reactor
+#[cfg(all(feature = "tokio-rt", not(feature = "async-rt")))]
+#[derive(Clone)]
+struct Source(Arc<tokio::io::unix::AsyncFd<Fd>>);
+
+#[cfg(all(feature = "tokio-rt", not(feature = "async-rt")))]
+impl std::os::unix::io::AsRawFd for Fd {
+	fn as_raw_fd(&self) -> RawFd {
+		self.0
+	}
+}
+
+#[cfg(all(feature = "tokio-rt", not(feature = "async-rt")))]
+impl Source {
+	fn new(fd: RawFd) -> io::Result<Self> {
+		tokio::io::unix::AsyncFd::new(Fd(fd)).map(|fd| Source(Arc::new(fd)))
+	}
+
+	fn readable(&self) -> Readable {
+		let fd = Arc::clone(&self.0);
+		Box::pin(async move {
+			// Every event is drained from the connection before
+			// waiting again, so readiness is cleared up front
+			fd.readable().await?.clear_ready();
+			Ok(())
+		})
+	}
+}
+
+/// The connection registered with the reactor. It is registered
+/// once, on first use, and shared by every stream of the connection.
+#[derive(Default)]
+pub(crate) struct Reactor(Mutex<Option<Source>>);
+
+impl Reactor {
+	fn source(&self, fd: Option<RawFd>) -> Result<Option<Source>, Error> {
+		let fd = match fd {
+			None => return Ok(None),
+			Some(fd) => fd
+		};
+		let mut source = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		if source.is_none() {
+			*source = Some(Source::new(fd).map_err(io_error)?);
+		}
+		Ok(source.clone())
+	}
+}
+
+/// Tasks waiting for a session that was busy handling a message
+#[derive(Default)]
+pub(crate) struct Parked(Mutex<Vec<Waker>>);
+
+impl Parked {
+	fn park(&self, waker: &Waker) {
+		let mut wakers = self.0.lock().unwrap_or_else(|e| e.into_inner());
+		if !wakers.iter().any(|w| w.will_wake(waker)) {
+			wakers.push(waker.clone());
+		}
+	}
+
+	/// Wake the parked tasks, called once the session is released
+	pub fn wake(&self) {
+		let wakers = std::mem::take(&mut *self.0.lock().unwrap_or_else(|e| e.into_inner()));
+		wakers.into_iter().for_each(Waker::wake);
+	}
+}
+
+fn io_error(e: io::Error) -> Error {
+	Error::Custom(e.to_string())
+}
+
+/// Wakes the stream at the earliest timer deadline using the async-std reactor
+#[cfg(feature = "async-rt")]
+struct Timer(async_io::Timer, Instant);
+
+#[cfg(feature = "async-rt")]
+impl Timer {
+	fn new(deadline: Instant) -> Self {
+		Timer(async_io::Timer::at(deadline), deadline)
+	}
+
+	fn reset(&mut self, deadline: Instant) {
+		if self.1 != deadline {
+			self.0.set_at(deadline);
+			self.1 = deadline;
+		}
+	}
+
+	fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+		Pin::new(&mut self.0).poll(cx).map(|_| ())
+	}
+}
+
+/// Wakes the stream at the earliest timer deadline using the tokio reactor
+#[cfg(all(feature = "tokio-rt", not(feature = "async-rt")))]
+struct Timer(Pin<Box<tokio::time::Sleep>>, Instant);
+
+#[cfg(all(feature = "tokio-rt", not(feature = "async-rt")))]
+impl Timer {
+	fn new(deadline: Instant) -> Self {
+		let sleep = tokio::time::sleep_until(deadline.into());
+		Timer(Box::pin(sleep), deadline)
+	}
+
+	fn reset(&mut self, deadline: Instant) {
+		if self.1 != deadline {
+			self.0.as_mut().reset(deadline.into());
+			self.1 = deadline;
+		}
+	}
+
+	fn poll_expired(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+		self.0.as_mut().poll(cx)
+	}
+}
+
+/// A `Stream` of the `Event`s of a single session.
+/// It is driven by the readiness of the connection
+/// so no thread is blocked waiting for an event.
+/// The stream ends when the system has no more events to deliver,
+/// such as a headless session with an empty queue and no timers.
+/// The stream is woken when a timer of the session expires.
+///
+/// With the `tokio-rt` feature the stream must be polled from within
+/// a tokio runtime. When both `async-rt` and `tokio-rt` are enabled,
+/// the async-std reactor is used.
+/// # Example
+///```no_run
+/// use std::{future::poll_fn, pin::Pin};
+/// use ren::futures_core::Stream;
+///
+/// async fn run(connect: &ren::Connection, session: &ren::Token) {
+///     let mut events = connect.events(session).unwrap();
+///
+///     while let Some(event) = poll_fn(|cx| Pin::new(&mut events).poll_next(cx)).await {
+///         if event == ren::Event::CloseRequested {
+///             break;
+///         }
+///     }
+/// }
+///```
+pub struct EventStream<'a> {
+	connection: &'a Connection,
+	token: Token,
+	session: Arc<RwLock<Session>>,
+	parked: Arc<Parked>,
+	source: Option<Source>,
+	readable: Option<Readable>,
+	timer: Option<Timer>
+}
+
+impl<'a> EventStream<'a> {
+	pub(crate) fn new(
+		connection: &'a Connection,
+		token: Token,
+		session: Arc<RwLock<Session>>,
+		reactor: &Reactor,
+		fd: Option<RawFd>
+	) -> Result<Self, Error> {
+		let parked = Arc::clone(session.try_read().map_err(|_| Error::Session)?.parked());
+		Ok(Self {
+			connection,
+			token,
+			session,
+			parked,
+			source: reactor.source(fd)?,
+			readable: None,
+			timer: None
+		})
+	}
+
+	/// Poll for the next event. Fails with `Error::Closed` once
+	/// the session is closed and `Error::NoEvent` when the system
+	/// has no more events to deliver.
+	pub(crate) fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<Event, Error>> {
+		loop {
+			let session = match self.session.try_read() {
+				Ok(session) => session,
+				Err(_) => {
+					// The session is busy handling a message,
+					// it wakes us once it is released
+					self.parked.park(cx.waker());
+					match self.session.try_read() {
+						Ok(session) => session,
+						Err(_) => return Poll::Pending
+					}
+				}
+			};
+
+			if let Some(event) = self.connection.inject(&self.token) {
+				return Poll::Ready(Ok(event));
+			}
+			match session.poll() {
+				Ok(event) => match self.connection.intercept_event(&self.token, event) {
+					None => continue,
+					Some(event) => return Poll::Ready(Ok(event))
+				},
+				Err(Error::NoEvent) => (),
+				Err(e) => return Poll::Ready(Err(e))
+			}
+
+			// Wake up again when the earliest timer expires
+			let deadline = session.timers().deadline();
+			if let Some(deadline) = deadline {
+				let timer = match &mut self.timer {
+					Some(timer) => {
+						timer.reset(deadline);
+						timer
+					},
+					timer => timer.get_or_insert_with(|| Timer::new(deadline))
+				};
+				if timer.poll_expired(cx).is_ready() {
+					continue;
+				}
+			}
+
+			let source = match &self.source {
+				None if deadline.is_some() => return Poll::Pending,
+				None => return Poll::Ready(Err(Error::NoEvent)),
+				Some(source) => source
+			};
+
+			// Events read by another session are routed to us
+			session.register_waker(cx.waker());
+			let readable = self.readable.get_or_insert_with(|| source.readable());
+			match readable.as_mut().poll(cx) {
+				Poll::Pending => return Poll::Pending,
+				Poll::Ready(ready) => {
+					self.readable = None;
+					ready.map_err(io_error)?;
+				}
+			}
+		}
+	}
+}
+
+impl Stream for EventStream<'_> {
+	type Item = Event;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		self.get_mut().poll_event(cx).map(Result::ok)
+	}
+}
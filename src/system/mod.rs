@@ -1,8 +1,8 @@
-
+use std::time::{Duration, Instant};
 use crate::{
-	Stat, Data, WindowCommand,
+	Stat, Data, WindowCommand, XcbCommand, Error, WindowAttributes,
 	event::Event,
-	context::{WindowContext, ConnectionError}
+	context::{WindowContext, ConnectionError, headless, mock}
 };
 
 #[cfg(target_family = "unix")]
@@ -13,7 +13,11 @@ use crate::context::xcb;
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SystemType {
 	#[cfg(target_family = "unix")]
-	Xcb
+	Xcb,
+	/// An in-memory system that requires no display server
+	Headless,
+	/// A scriptable system for testing, see `Mock`
+	Mock
 }
 
 impl Default for SystemType {
@@ -21,67 +25,349 @@ impl Default for SystemType {
 	fn default() -> Self {
 		SystemType::Xcb
 	}
+
+	#[cfg(not(target_family = "unix"))]
+	fn default() -> Self {
+		SystemType::Headless
+	}
 }
 
 pub enum Window {
 	#[cfg(target_family = "unix")]
-	Xcb(xcb::Window)
+	Xcb(xcb::Window),
+	Headless(headless::Window),
+	Mock(mock::Window)
 }
 
 impl Window {
-	#[cfg(target_family = "unix")]
+	/// The id of the window in the system, if it has one
+	pub fn id(&self) -> Option<u32> {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => Some(w.xid()),
+			Headless(w) => Some(w.id()),
+			Mock(w) => Some(w.id())
+		}
+	}
+
+	/// Move the window into `parent`, or make it a top-level window
+	pub fn reparent(&self, parent: Option<&Window>, origin: (u32, u32)) -> Result<(), Error> {
+		use Window::*;
+		match (self, parent) {
+			#[cfg(target_family = "unix")]
+			(Xcb(w), None) => w.reparent(None, origin),
+			#[cfg(target_family = "unix")]
+			(Xcb(w), Some(Xcb(p))) => w.reparent(Some(p), origin),
+			(Headless(w), None) => w.reparent(None, origin),
+			(Headless(w), Some(Headless(p))) => w.reparent(Some(p), origin),
+			(Mock(w), None) => w.reparent(None, origin),
+			(Mock(w), Some(Mock(p))) => w.reparent(Some(p), origin),
+			_ => Err(Error::Session)
+		}
+	}
+
+	/// Wake `waker` once an event is routed to the window
+	/// by another reader of the connection
+	#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+	pub fn register_waker(&self, waker: &std::task::Waker) {
+		match self {
+			#[cfg(target_family = "unix")]
+			Window::Xcb(w) => w.register_waker(waker),
+			_ => ()
+		}
+	}
+
+	pub fn proxy(&self) -> Proxy {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => Proxy::Xcb(w.proxy()),
+			Headless(w) => Proxy::Headless(w.proxy()),
+			Mock(w) => Proxy::Mock(w.proxy())
+		}
+	}
+
 	pub fn event(&self) -> Option<Event> {
 		use Window::*;
 		match self {
-			Xcb(w) => w.event()
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.event(),
+			Headless(w) => w.event(),
+			Mock(w) => w.event()
 		}
 	}
 	
-	#[cfg(target_family = "unix")]
+	pub fn event_timeout(&self, timeout: Duration) -> Option<Event> {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.event_timeout(timeout),
+			Headless(w) => w.event_timeout(timeout),
+			Mock(w) => w.event_timeout(timeout)
+		}
+	}
+
 	pub fn poll(&self) -> Option<Event> {
 		use Window::*;
 		match self {
-			Xcb(w) => w.poll()
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.poll(),
+			Headless(w) => w.poll(),
+			Mock(w) => w.poll()
 		}
 	}
 	
-	#[cfg(target_family = "unix")]
 	pub fn stat(&self, stat: Stat) -> Option<Data> {
 		use Window::*;
 		match self {
-			Xcb(w) => w.stat(stat)
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.stat(stat),
+			Headless(w) => w.stat(stat),
+			Mock(w) => w.stat(stat)
 		}
 	}
 	
-	#[cfg(target_family = "unix")]
+	pub fn stat_checked(&self, stat: Stat) -> Result<Option<Data>, Error> {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.stat_checked(stat),
+			Headless(w) => w.stat_checked(stat),
+			Mock(w) => w.stat_checked(stat)
+		}
+	}
+
+	/// Send a stat without waiting for its reply
+	pub fn stat_async(&self, stat: Stat) -> Pending {
+		use Window::*;
+		match (self, stat) {
+			#[cfg(target_family = "unix")]
+			(Xcb(w), Stat::Window(window)) => match w.pending(window) {
+				Some(pending) => Pending::Xcb(pending),
+				None => Pending::Ready(self.stat(stat))
+			},
+			_ => Pending::Ready(self.stat(stat))
+		}
+	}
+
 	pub fn window(&self, command: &WindowCommand) {
 		use Window::*;
 		match self {
-			Xcb(w) => w.window(command)
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.window(command),
+			Headless(w) => w.window(command),
+			Mock(w) => w.window(command)
+		}
+	}
+
+	/// Handle an XCB command, other systems fail with `Error::Type`
+	pub fn xcb(&self, checked: bool, command: &XcbCommand) -> Result<(), Error> {
+		match self {
+			#[cfg(target_family = "unix")]
+			Window::Xcb(w) => w.xcb_command(checked, command),
+			_ => {
+				let _ = (checked, command);
+				Err(Error::Type)
+			}
+		}
+	}
+
+	pub fn closed(&self) -> bool {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.closed(),
+			Headless(w) => w.closed(),
+			Mock(w) => w.closed()
+		}
+	}
+
+	/// Flush pending commands to the system
+	pub fn update(&self) {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.update(),
+			Headless(w) => w.update(),
+			Mock(w) => w.update()
+		}
+	}
+
+	pub fn validate(&self, command: &WindowCommand) -> Result<(), Error> {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.validate(command),
+			Headless(w) => w.validate(command),
+			Mock(w) => w.validate(command)
+		}
+	}
+
+	pub fn window_checked(&self, command: &WindowCommand) -> Result<(), Error> {
+		use Window::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(w) => w.window_checked(command),
+			Headless(w) => w.window_checked(command),
+			Mock(w) => w.window_checked(command)
+		}
+	}
+}
+
+/// The reply to a stat that was sent without waiting for it
+pub enum Pending {
+	#[cfg(target_family = "unix")]
+	Xcb(xcb::Pending),
+	/// The reply was known when the stat was sent
+	Ready(Option<Data>)
+}
+
+impl Pending {
+	/// Take the reply if it arrived, `None` while it is outstanding
+	pub fn poll(&mut self) -> Option<Option<Data>> {
+		match self {
+			#[cfg(target_family = "unix")]
+			Pending::Xcb(p) => p.poll(),
+			Pending::Ready(data) => Some(data.take())
+		}
+	}
+
+	/// Wait for the reply
+	pub fn wait(&mut self) -> Option<Data> {
+		match self {
+			#[cfg(target_family = "unix")]
+			Pending::Xcb(p) => p.wait(),
+			Pending::Ready(data) => data.take()
+		}
+	}
+}
+
+/// Posts events to a window from any thread
+#[derive(Clone)]
+pub enum Proxy {
+	#[cfg(target_family = "unix")]
+	Xcb(xcb::Proxy),
+	Headless(headless::Proxy),
+	Mock(mock::Proxy)
+}
+
+impl Proxy {
+	/// Queue `event` on the window, fails if the window is gone
+	pub fn post(&self, event: Event) -> bool {
+		use Proxy::*;
+		match self {
+			#[cfg(target_family = "unix")]
+			Xcb(p) => p.post(event),
+			Headless(p) => p.post(event),
+			Mock(p) => p.post(event)
 		}
 	}
 }
 
 enum SystemConnection {
 	#[cfg(target_family = "unix")]
-	Xcb(xcb::Connection)
+	Xcb(xcb::Connection),
+	Headless(headless::Connection),
+	Mock(mock::Connection)
 }
 
 impl SystemConnection {
-	#[cfg(target_family = "unix")]
-	fn new(ty: SystemType) -> Result<Self, Option<ConnectionError>> {
+	fn new(ty: SystemType) -> Result<Self, ConnectionError> {
 		match ty {
+			#[cfg(target_family = "unix")]
 			SystemType::Xcb => {
 				Ok(SystemConnection::Xcb(xcb::Connection::open()?))
+			},
+			SystemType::Headless => {
+				Ok(SystemConnection::Headless(headless::Connection::open()?))
+			},
+			SystemType::Mock => {
+				Ok(SystemConnection::Mock(mock::Connection::open()?))
 			}
 		}
 	}
 	
-	#[inline]
+	/// The file descriptor of the connection, if there is one
 	#[cfg(target_family = "unix")]
-	fn create_window(&self) -> Window {
+	fn fd(&self) -> Option<std::os::unix::io::RawFd> {
+		use std::os::unix::io::AsRawFd;
 		match self {
-			SystemConnection::Xcb(c) => Window::Xcb(c.into())
+			SystemConnection::Xcb(c) => Some(c.as_raw_fd()),
+			_ => None
+		}
+	}
+
+	/// The number of times a headless or mock window was notified of an event
+	fn generation(&self) -> u64 {
+		match self {
+			#[cfg(target_family = "unix")]
+			SystemConnection::Xcb(_) => 0,
+			SystemConnection::Headless(c) => c.signal().generation(),
+			SystemConnection::Mock(c) => c.signal().generation()
+		}
+	}
+
+	/// Wait for an event of any window. An event read from the system is
+	/// returned with the id of its window. Headless and mock windows queue
+	/// their own events, the wait ends once one is notified after `generation`.
+	/// The wait also ends at `deadline`, if any.
+	fn event(&self, generation: u64, deadline: Option<Instant>) -> Option<(u32, Event)> {
+		match self {
+			#[cfg(target_family = "unix")]
+			SystemConnection::Xcb(c) => {
+				let _ = generation;
+				c.event(deadline)
+			},
+			SystemConnection::Headless(c) => {
+				c.signal().wait(generation, deadline);
+				None
+			},
+			SystemConnection::Mock(c) => {
+				c.signal().wait(generation, deadline);
+				None
+			}
+		}
+	}
+
+	/// Poll for an event of any window that has an id
+	fn poll(&self) -> Option<(u32, Event)> {
+		match self {
+			#[cfg(target_family = "unix")]
+			SystemConnection::Xcb(c) => c.poll(),
+			_ => None
+		}
+	}
+
+	#[inline]
+	fn create_window(&self, attributes: &WindowAttributes, parent: Option<&Window>) -> Window {
+		match (self, parent) {
+			#[cfg(target_family = "unix")]
+			(SystemConnection::Xcb(c), Some(Window::Xcb(p))) => {
+				Window::Xcb(xcb::Window::create(c, attributes, Some(p)))
+			},
+			#[cfg(target_family = "unix")]
+			(SystemConnection::Xcb(c), _) => Window::Xcb(xcb::Window::create(c, attributes, None)),
+			(SystemConnection::Headless(c), Some(Window::Headless(p))) => {
+				Window::Headless(headless::Window::create(c, attributes, Some(p)))
+			},
+			(SystemConnection::Headless(c), _) => {
+				Window::Headless(headless::Window::create(c, attributes, None))
+			},
+			(SystemConnection::Mock(c), Some(Window::Mock(p))) => {
+				Window::Mock(mock::Window::create(c, attributes, Some(p)))
+			},
+			(SystemConnection::Mock(c), _) => Window::Mock(mock::Window::create(c, attributes, None))
+		}
+	}
+
+	#[inline]
+	fn adopt_window(&self, id: u32) -> Result<Window, Error> {
+		match self {
+			#[cfg(target_family = "unix")]
+			SystemConnection::Xcb(c) => xcb::Window::adopt(c, id).map(Window::Xcb),
+			SystemConnection::Headless(c) => headless::Window::adopt(c, id).map(Window::Headless),
+			SystemConnection::Mock(c) => mock::Window::adopt(c, id).map(Window::Mock)
 		}
 	}
 }
@@ -92,14 +378,41 @@ pub struct System {
 }
 
 impl System {
-	pub fn new(ty: SystemType) -> Result<Self, Option<ConnectionError>> {
+	pub fn new(ty: SystemType) -> Result<Self, ConnectionError> {
 		Ok(Self {
 			ty,
 			connection: SystemConnection::new(ty)?
 		})
 	}
+
+	pub fn ty(&self) -> SystemType {
+		self.ty
+	}
 	
-	pub fn create_window(&self) -> Window {
-		self.connection.create_window()
+	/// Create a window, inside `parent` if there is one
+	pub fn create_window(&self, attributes: &WindowAttributes, parent: Option<&Window>) -> Window {
+		self.connection.create_window(attributes, parent)
+	}
+
+	/// Wrap the existing window `id` of another client
+	pub fn adopt_window(&self, id: u32) -> Result<Window, Error> {
+		self.connection.adopt_window(id)
+	}
+
+	#[cfg(target_family = "unix")]
+	pub fn fd(&self) -> Option<std::os::unix::io::RawFd> {
+		self.connection.fd()
+	}
+
+	pub fn generation(&self) -> u64 {
+		self.connection.generation()
+	}
+
+	pub fn event(&self, generation: u64, deadline: Option<Instant>) -> Option<(u32, Event)> {
+		self.connection.event(generation, deadline)
+	}
+
+	pub fn poll(&self) -> Option<(u32, Event)> {
+		self.connection.poll()
 	}
 }
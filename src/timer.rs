@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+use crate::Token;
+
+struct Timer {
+	token: Token,
+	deadline: Instant,
+	period: Option<Duration>
+}
+
+/// The timers scheduled on a session
+#[derive(Default)]
+pub struct Timers {
+	timers: Vec<Timer>
+}
+
+impl Timers {
+	/// Schedule a timer to expire after `delay`, then every `period`
+	pub fn add(&mut self, delay: Duration, period: Option<Duration>) -> Token {
+		let mut token = Token::new();
+		while self.timers.iter().any(|t| t.token == token) {
+			token = Token::new();
+		}
+		self.timers.push(Timer {
+			token,
+			deadline: Instant::now() + delay,
+			period
+		});
+		token
+	}
+
+	/// Cancel a timer, returns false if there is no such timer
+	pub fn cancel(&mut self, token: &Token) -> bool {
+		let len = self.timers.len();
+		self.timers.retain(|t| t.token != *token);
+		len != self.timers.len()
+	}
+
+	/// The earliest deadline of all timers
+	pub fn deadline(&self) -> Option<Instant> {
+		self.timers.iter().map(|t| t.deadline).min()
+	}
+
+	/// Take the earliest timer that has expired. A repeating
+	/// timer is scheduled again, others are removed.
+	pub fn expire(&mut self) -> Option<Token> {
+		let now = Instant::now();
+		let (i, timer) = self.timers.iter_mut()
+			.enumerate()
+			.filter(|(_, t)| t.deadline <= now)
+			.min_by_key(|(_, t)| t.deadline)?;
+		let token = timer.token;
+		match timer.period {
+			// Skip any periods that were missed
+			Some(period) if !period.is_zero() => {
+				while timer.deadline <= now {
+					timer.deadline += period;
+				}
+			},
+			_ => { self.timers.remove(i); }
+		}
+		Some(token)
+	}
+}
@@ -4,15 +4,75 @@ extern crate ren;
 fn main()
 {
     // Open a connection
-    let mut connect = ren::Connection::open().unwrap();
+    let mut connect = ren::Connection::open_with(ren::SystemType::Headless).unwrap();
     let token = connect.begin();
 
     // Assert that it is active
-    assert_eq!(true, connect.active(&token));
+    assert!(connect.active(&token));
 
     // End the connection
-    connect.end(&token);
+    connect.end(&token).unwrap();
 
     // Assert that it is not active
-    assert_eq!(false, connect.active(&token));
+    assert!(!connect.active(&token));
+}
+
+#[test]
+fn terminate()
+{
+    use ren::{Event, Error, Mock, WindowCommand::Map};
+
+    let mut connect = ren::Connection::open_with(ren::SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    mock.script(vec![Event::Terminate]);
+    assert!(connect.active(&token));
+
+    // A delivered terminate closes the session
+    assert_eq!(connect.wait(&token), Ok(Event::Terminate));
+    assert!(!connect.active(&token));
+    assert_eq!(connect.request(&token, Map), Err(Error::Closed));
+    assert_eq!(connect.wait(&token), Err(Error::Closed));
+}
+
+#[test]
+fn destroyed()
+{
+    use ren::{Event, Error, Mock, WindowCommand::Map};
+
+    let mut connect = ren::Connection::open_with(ren::SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    mock.script(vec![Event::Unknown(None)]);
+    mock.close();
+    assert!(!connect.active(&token));
+    assert_eq!(connect.request(&token, Map), Err(Error::Closed));
+
+    // Queued events are still delivered
+    assert_eq!(connect.poll(&token), Ok(Event::Unknown(None)));
+    assert_eq!(connect.poll(&token), Ok(Event::Terminate));
+    assert_eq!(connect.poll(&token), Err(Error::Closed));
+    assert!(mock.commands().is_empty());
+}
+
+#[test]
+fn close()
+{
+    use ren::{Event, Error, Mock, WindowCommand::Close};
+
+    let mut connect = ren::Connection::open_with(ren::SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    // A close request leaves the window open
+    mock.script(vec![Event::CloseRequested]);
+    assert_eq!(connect.wait(&token), Ok(Event::CloseRequested));
+    assert!(connect.active(&token));
+
+    connect.request(&token, Close).unwrap();
+    assert!(!connect.active(&token));
+    assert_eq!(connect.wait(&token), Ok(Event::Terminate));
+    assert_eq!(connect.wait(&token), Err(Error::Closed));
 }
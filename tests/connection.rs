@@ -0,0 +1,80 @@
+extern crate ren;
+
+use ren::{SystemType, ConnectionError};
+
+#[test]
+fn message()
+{
+    let error = ConnectionError::InvalidScreen(2);
+    assert_eq!(error.to_string(), "the display server has no screen 2");
+}
+
+#[test]
+fn handler()
+{
+    use ren::{Message, Type, Body, Error, data::WindowData, stat::WindowStat};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    let layout = || Message::new(Type::Custom("layout".into()), Body::Custom("save".into()));
+    assert_eq!(connect.send(&session, layout()), Err(Error::Type));
+
+    connect.register("layout", |connect, token, message| {
+        match (message.body(), connect.request(token, WindowStat::Dimension)?.take_body()) {
+            (Body::Custom(action), Body::Data(ren::Data::Window(WindowData::Dimension((w, h))))) => {
+                Ok(Message::response(Body::Custom(format!("{} {}x{}", action, w, h))))
+            },
+            _ => Err(Error::Type)
+        }
+    });
+    let response = connect.send(&session, layout()).unwrap();
+    assert_eq!(response.body(), &Body::Custom("save 1x1".into()));
+
+    connect.end(&session).unwrap();
+    assert_eq!(connect.send(&session, layout()), Err(Error::Token));
+
+    assert!(connect.unregister("layout"));
+    assert!(!connect.unregister("layout"));
+}
+
+#[test]
+fn handler_paths()
+{
+    use ren::{Message, MessageQueue, Type, Body, Error, data::WindowData, stat::WindowStat, WindowCommand::*};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    let layout = || Message::new(Type::Custom("layout".into()), Body::None);
+    connect.register("layout", |connect, token, _| {
+        match connect.request(token, WindowStat::Dimension)?.take_body() {
+            Body::Data(ren::Data::Window(WindowData::Dimension((w, h)))) => {
+                Ok(Message::response(Body::Custom(format!("{}x{}", w, h))))
+            },
+            _ => Err(Error::Type)
+        }
+    });
+
+    // The handler sees the commands sent before it in the batch
+    let mut queue = MessageQueue::new();
+    queue.enqueue(Message::request(Dimension((20, 10))));
+    queue.enqueue(layout());
+    queue.enqueue(Message::new(Type::Custom("missing".into()), Body::None));
+    let batch = connect.batch(&session, queue).unwrap();
+    let responses = connect.dispatch(&session, &batch).unwrap();
+    assert_eq!(responses[1].as_ref().map(Message::body), Ok(&Body::Custom("20x10".into())));
+    assert_eq!(responses[2], Err(Error::Type));
+
+    // A validated queue rejects a type without a handler up front
+    let mut queue = MessageQueue::new();
+    queue.validate(true);
+    queue.enqueue(layout());
+    queue.enqueue(Message::new(Type::Custom("missing".into()), Body::None));
+    let batch = connect.batch(&session, queue).unwrap();
+    assert_eq!(connect.dispatch(&session, &batch), Err(Error::Type));
+
+    let id = connect.send_async(&session, layout()).unwrap();
+    let response = connect.reply(&session, &id).unwrap();
+    assert_eq!(response.id(), Some(&id));
+    assert_eq!(response.body(), &Body::Custom("20x10".into()));
+    assert!(connect.send_async(&session, Message::new(Type::Custom("missing".into()), Body::None)).is_err());
+}
@@ -0,0 +1,18 @@
+#![cfg(all(feature = "raw-window-handle", target_family = "unix"))]
+
+extern crate ren;
+
+use ren::{SystemType, Error, WindowHandle};
+
+#[test]
+fn headless()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    // Only X windows have a raw handle
+    assert_eq!(WindowHandle::new(&connect, &token).map(|h| h.window()), Err(Error::Type));
+
+    connect.end(&token).unwrap();
+    assert_eq!(WindowHandle::new(&connect, &token).map(|h| h.window()), Err(Error::Token));
+}
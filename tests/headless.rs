@@ -0,0 +1,261 @@
+extern crate ren;
+
+use ren::{
+    SystemType, Body, Data, Event, DisplayEvent, Error,
+    data::WindowData, stat::WindowStat,
+    WindowCommand::*
+};
+
+fn stat(connect: &ren::Connection, token: &ren::Token, stat: WindowStat) -> WindowData
+{
+    match connect.request(token, stat).unwrap().take_body() {
+        Body::Data(Data::Window(data)) => data,
+        body => panic!("unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn state()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    assert_eq!(connect.system(), SystemType::Headless);
+
+    let status = connect.requests(&token, &[
+        Title("headless".into()),
+        Dimension((640, 480)),
+        Origin((10, 20)),
+        StackAbove,
+        Clear,
+        Update
+    ]);
+    assert!(status.iter().all(|s| s.is_ok()));
+
+    assert_eq!(stat(&connect, &token, WindowStat::Title), WindowData::Title("headless".into()));
+    assert_eq!(stat(&connect, &token, WindowStat::Dimension), WindowData::Dimension((640, 480)));
+    assert_eq!(stat(&connect, &token, WindowStat::Position), WindowData::Position((10, 20)));
+    assert_eq!(stat(&connect, &token, WindowStat::Mapped), WindowData::Mapped(false));
+
+    connect.request(&token, Map).unwrap();
+    assert_eq!(stat(&connect, &token, WindowStat::Mapped), WindowData::Mapped(true));
+
+    connect.request(&token, Unmap).unwrap();
+    assert_eq!(stat(&connect, &token, WindowStat::Mapped), WindowData::Mapped(false));
+}
+
+#[test]
+fn stacking()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let bottom = connect.begin();
+    let top = connect.begin();
+
+    // New windows are stacked above their siblings
+    assert_eq!(stat(&connect, &bottom, WindowStat::Stacking), WindowData::Stacking(0));
+    assert_eq!(stat(&connect, &top, WindowStat::Stacking), WindowData::Stacking(1));
+
+    connect.request(&bottom, StackAbove).unwrap();
+    assert_eq!(stat(&connect, &bottom, WindowStat::Stacking), WindowData::Stacking(1));
+    assert_eq!(stat(&connect, &top, WindowStat::Stacking), WindowData::Stacking(0));
+
+    connect.request(&bottom, StackBelow).unwrap();
+    assert_eq!(stat(&connect, &bottom, WindowStat::Stacking), WindowData::Stacking(0));
+
+    // Children are only stacked among themselves
+    let child = connect.begin_child(&top, ren::WindowAttributes::new()).unwrap();
+    assert_eq!(stat(&connect, &child, WindowStat::Stacking), WindowData::Stacking(0));
+
+    connect.end(&bottom).unwrap();
+    assert_eq!(stat(&connect, &top, WindowStat::Stacking), WindowData::Stacking(0));
+}
+
+#[test]
+fn events()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    // Nothing has happened yet
+    assert_eq!(connect.poll(&token), Err(ren::Error::NoEvent));
+
+    connect.requests(&token, &[Dimension((320, 240)), Map]);
+
+    assert_eq!(connect.poll(&token), Ok(DisplayEvent::Resize((320, 240)).into()));
+    match connect.poll(&token) {
+        Ok(Event::Display(DisplayEvent::Expose(map))) => assert_eq!(map.1, (320, 240)),
+        event => panic!("unexpected event {:?}", event)
+    }
+    assert_eq!(connect.poll(&token), Err(ren::Error::NoEvent));
+}
+
+#[test]
+fn checked()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    // Unchecked commands always succeed
+    assert_eq!(connect.request(&token, Dimension((0, 0))), Ok(ren::Message::empty()));
+
+    connect.checked(&token, true).unwrap();
+    assert!(matches!(connect.request(&token, Dimension((0, 10))), Err(Error::Protocol {
+        code: 2,
+        major: 12,
+        ..
+    })));
+    assert_eq!(connect.request(&token, Dimension((10, 10))), Ok(ren::Message::empty()));
+}
+
+#[test]
+fn attributes()
+{
+    use ren::{WindowAttributes, event::EventSet};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin_with(WindowAttributes::new()
+        .title("attributes")
+        .dimension((320, 240))
+        .origin((5, 6))
+        .visual(0, 24));
+
+    assert_eq!(stat(&connect, &token, WindowStat::Title), WindowData::Title("attributes".into()));
+    assert_eq!(stat(&connect, &token, WindowStat::Dimension), WindowData::Dimension((320, 240)));
+    assert_eq!(stat(&connect, &token, WindowStat::Position), WindowData::Position((5, 6)));
+    assert_eq!(stat(&connect, &token, WindowStat::Depth), WindowData::Depth(24));
+
+    // Created at its size, so there is nothing to report
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+
+    // Only exposure events are delivered
+    let token = connect.begin_with(WindowAttributes::new().events(EventSet::EXPOSURE));
+    connect.requests(&token, &[Dimension((10, 10)), Map]);
+    assert_eq!(connect.wait(&token), Ok(DisplayEvent::Expose(ren::event::display::Map((0, 0), (10, 10))).into()));
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+}
+
+#[test]
+fn child()
+{
+    use ren::WindowAttributes;
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let main = connect.begin();
+    let other = connect.begin();
+    let child = connect.begin_child(&main, WindowAttributes::new().origin((10, 10))).unwrap();
+    let grandchild = connect.begin_child(&child, WindowAttributes::new()).unwrap();
+
+    let parent = |token| match stat(&connect, token, WindowStat::Parent) {
+        WindowData::Parent(parent) => parent,
+        data => panic!("unexpected data {:?}", data)
+    };
+    assert_eq!(parent(&main), None);
+    let first = parent(&child);
+    assert!(first.is_some());
+    assert_eq!(stat(&connect, &child, WindowStat::Position), WindowData::Position((10, 10)));
+
+    // The child has its own events
+    connect.request(&child, Dimension((20, 20))).unwrap();
+    assert_eq!(connect.poll(&main), Err(Error::NoEvent));
+    assert_eq!(connect.wait(&child), Ok(DisplayEvent::Resize((20, 20)).into()));
+
+    connect.reparent(&child, Some(&other), (1, 2)).unwrap();
+    assert!(parent(&child).is_some());
+    assert_ne!(parent(&child), first);
+    assert_eq!(stat(&connect, &child, WindowStat::Position), WindowData::Position((1, 2)));
+
+    connect.reparent(&child, None, (0, 0)).unwrap();
+    assert_eq!(parent(&child), None);
+
+    // A window can not be its own parent, or the child of a descendant
+    assert!(matches!(connect.reparent(&child, Some(&child), (0, 0)), Err(Error::Protocol { .. })));
+    assert!(matches!(
+        connect.reparent(&child, Some(&grandchild), (0, 0)),
+        Err(Error::Protocol { code: 8, major: 7, .. })
+    ));
+    connect.reparent(&main, Some(&child), (0, 0)).unwrap();
+    assert!(matches!(
+        connect.reparent(&child, Some(&main), (0, 0)),
+        Err(Error::Protocol { code: 8, major: 7, .. })
+    ));
+    assert_eq!(parent(&child), None);
+
+    let unknown = ren::Connection::open_with(SystemType::Headless).unwrap().begin();
+    assert_eq!(connect.begin_child(&unknown, WindowAttributes::new()), Err(Error::Token));
+}
+
+#[test]
+fn embed()
+{
+    use ren::{WindowAttributes, XcbCommand};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin_with(WindowAttributes::new().embed(0x400001));
+    assert_eq!(stat(&connect, &token, WindowStat::Parent), WindowData::Parent(Some(0x400001)));
+
+    // Only an X server can host a foreign window
+    assert_eq!(connect.request(&token, XcbCommand::Embed(0x400002)), Err(Error::Type));
+    assert_eq!(connect.request(&token, XcbCommand::FocusIn(0x400002)), Err(Error::Type));
+}
+
+#[test]
+fn adopt()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    assert!(matches!(connect.adopt(0), Err(Error::Protocol { code: 3, .. })));
+
+    let token = connect.adopt(0x500001).unwrap();
+    connect.request(&token, Title("adopted".into())).unwrap();
+    assert_eq!(stat(&connect, &token, WindowStat::Title), WindowData::Title("adopted".into()));
+    assert_eq!(connect.adopt(0x500001), Err(Error::Session));
+
+    connect.end(&token).unwrap();
+    assert!(connect.adopt(0x500001).is_ok());
+}
+
+#[test]
+fn subscribe()
+{
+    use ren::event::EventSet;
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    connect.request(&token, Subscribe(EventSet::EXPOSURE)).unwrap();
+
+    connect.requests(&token, &[Dimension((32, 32)), Map]);
+    let expose = DisplayEvent::Expose(ren::event::display::Map((0, 0), (32, 32)));
+    assert_eq!(connect.poll(&token), Ok(expose.into()));
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+
+    connect.request(&token, Subscribe(EventSet::all() - EventSet::MOTION)).unwrap();
+    connect.request(&token, Dimension((16, 16))).unwrap();
+    assert_eq!(connect.poll(&token), Ok(DisplayEvent::Resize((16, 16)).into()));
+}
+
+#[test]
+#[cfg(feature = "render")]
+fn render()
+{
+    use ren::{data::HeadlessData, stat::HeadlessStat, graphics::{Surface, Context}};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    connect.request(&token, Dimension((4, 4))).unwrap();
+
+    let surface = Surface::window(&connect, &token, (4, 4)).unwrap();
+    let mut cx = Context::new();
+    cx.rgb(1.0, 0.0, 0.0);
+    cx.paint(1.0);
+    surface.render(&cx);
+
+    let window = match connect.request(&token, HeadlessStat::Surface).unwrap().take_body() {
+        Body::Data(Data::Headless(HeadlessData::Surface(window))) => window,
+        body => panic!("unexpected body {:?}", body)
+    };
+    let (data, stride) = window.data().unwrap();
+    assert_eq!(data.len(), stride as usize * 4);
+    // ARgb32 is native endian, opaque red is 0xffff0000
+    assert!(data.chunks(4).all(|p| u32::from_ne_bytes([p[0], p[1], p[2], p[3]]) == 0xffff0000));
+
+    connect.request(&token, Clear).unwrap();
+    assert!(window.data().unwrap().0.iter().all(|b| *b == 0));
+}
@@ -0,0 +1,152 @@
+extern crate ren;
+
+use std::sync::{Arc, Mutex};
+use ren::{
+    Mock, SystemType, Token, Message, MessageQueue, Body, Command, Event, DisplayEvent, Error,
+    Interceptor, WindowCommand::*
+};
+
+/// Logs every message and vetoes `Close`
+#[derive(Default)]
+struct Audit(Arc<Mutex<Vec<Message>>>);
+
+impl Interceptor for Audit {
+    fn message(&self, _: &Token, message: Message) -> Result<Option<Message>, Error> {
+        self.0.lock().unwrap().push(message.clone());
+        match message.body() {
+            Body::Command(Command::Window(Close)) => Err(Error::Custom("vetoed".into())),
+            _ => Ok(Some(message))
+        }
+    }
+}
+
+/// Drops `Update`, resize events and injects a user event once
+#[derive(Default)]
+struct Filter(Mutex<Option<Event>>);
+
+impl Interceptor for Filter {
+    fn message(&self, _: &Token, message: Message) -> Result<Option<Message>, Error> {
+        match message.body() {
+            Body::Command(Command::Window(Update)) => Ok(None),
+            _ => Ok(Some(message))
+        }
+    }
+
+    fn event(&self, _: &Token, event: Event) -> Option<Event> {
+        match event {
+            Event::Display(DisplayEvent::Resize(_)) => None,
+            event => Some(event)
+        }
+    }
+
+    fn inject(&self, _: &Token) -> Option<Event> {
+        self.0.lock().unwrap().take()
+    }
+}
+
+#[test]
+fn messages()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    let audit = Audit::default();
+    let log = Arc::clone(&audit.0);
+    connect.intercept(audit);
+    connect.intercept(Filter::default());
+    connect.requests(&token, &[Map, Update]);
+    assert_eq!(connect.request(&token, Close), Err(Error::Custom("vetoed".into())));
+    assert_eq!(mock.commands(), vec![Map]);
+    assert_eq!(log.lock().unwrap().len(), 3);
+
+    // A veto cancels the whole batch
+    let mut queue = MessageQueue::new();
+    queue.enqueue(Message::request(Unmap));
+    queue.enqueue(Message::request(Close));
+    let batch = connect.batch(&token, queue).unwrap();
+    assert!(connect.dispatch(&token, &batch).is_err());
+    assert_eq!(mock.commands(), vec![Map]);
+}
+
+#[test]
+fn events()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    connect.intercept(Filter(Mutex::new(Some(Event::User("injected".into())))));
+    let token = connect.begin();
+
+    connect.requests(&token, &[Dimension((8, 8)), Map]);
+    assert_eq!(connect.poll(&token), Ok(Event::User("injected".into())));
+    assert!(matches!(connect.poll(&token), Ok(Event::Display(DisplayEvent::Expose(_)))));
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+}
+
+#[test]
+fn any()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    connect.intercept(Filter(Mutex::new(Some(Event::User("injected".into())))));
+    let token = connect.begin();
+
+    connect.requests(&token, &[Dimension((8, 8)), Map]);
+    assert_eq!(connect.poll_any(), Ok((token, Event::User("injected".into()))));
+    assert!(matches!(connect.poll_any(), Ok((_, Event::Display(DisplayEvent::Expose(_))))));
+    assert_eq!(connect.poll_any(), Err(Error::NoEvent));
+
+    connect.requests(&token, &[Dimension((16, 16))]);
+    let events = connect.drain();
+    assert!(!events.is_empty());
+    assert!(!events.iter().any(|(_, e)| matches!(e, Event::Display(DisplayEvent::Resize(_)))));
+}
+
+#[test]
+#[cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+fn stream()
+{
+    use std::{pin::Pin, task::{Context, Poll, Waker}};
+    use ren::futures_core::Stream;
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    connect.intercept(Filter(Mutex::new(Some(Event::User("injected".into())))));
+    let token = connect.begin();
+    connect.requests(&token, &[Dimension((8, 8)), Map]);
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut events = connect.events(&token).unwrap();
+    let mut next = || Pin::new(&mut events).poll_next(&mut cx);
+    assert_eq!(next(), Poll::Ready(Some(Event::User("injected".into()))));
+    assert!(matches!(next(), Poll::Ready(Some(Event::Display(DisplayEvent::Expose(_))))));
+    assert_eq!(next(), Poll::Ready(None));
+}
+
+/// Always has an event to inject
+struct Heartbeat(Event);
+
+impl Interceptor for Heartbeat {
+    fn inject(&self, _: &Token) -> Option<Event> {
+        Some(self.0.clone())
+    }
+}
+
+#[test]
+fn heartbeat()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    connect.intercept(Heartbeat(Event::User("beat".into())));
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+    mock.script(vec![Event::Unknown(None)]);
+
+    // The drain ends, taking one injected event
+    let beat = (token, Event::User("beat".into()));
+    assert_eq!(connect.drain(), vec![beat.clone(), (token, Event::Unknown(None))]);
+    assert_eq!(connect.drain(), vec![beat]);
+
+    // An injected terminate ends the events of the session
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    connect.intercept(Heartbeat(Event::Terminate));
+    let token = connect.begin();
+    Mock::session(&connect, &token).unwrap().script(vec![Event::Unknown(None)]);
+    assert_eq!(connect.drain(), vec![(token, Event::Terminate)]);
+}
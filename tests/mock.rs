@@ -0,0 +1,167 @@
+extern crate ren;
+
+use std::{thread, time::Duration};
+use ren::{
+    Mock, SystemType, Event, DisplayEvent, KeyEvent, Stat,
+    event::input::{KeyInput, KeyMap},
+    stat::WindowStat,
+    WindowCommand::*
+};
+
+/// A small application that hides the window on `Escape`
+fn run(connect: &ren::Connection, token: &ren::Token)
+{
+    loop {
+        match connect.wait(token) {
+            Ok(Event::Terminate) | Err(_) => break,
+            Ok(Event::Display(DisplayEvent::Resize(_))) => {
+                connect.request(token, WindowStat::Dimension).unwrap();
+            },
+            Ok(event) => {
+                if let Some(KeyEvent::Press(key)) = event.key() {
+                    if key.map() == Some(KeyMap::Escape) {
+                        connect.request(token, Unmap).unwrap();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn script()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    let escape = KeyInput::from_map(KeyMap::Escape, None).unwrap();
+    let space = KeyInput::from_map(KeyMap::Space, None).unwrap();
+    mock.script(vec![
+        KeyEvent::Press(space).into(),
+        DisplayEvent::Resize((320, 240)).into(),
+        KeyEvent::Press(escape).into(),
+        KeyEvent::Release(escape).into(),
+        Event::Terminate
+    ]);
+
+    run(&connect, &token);
+
+    assert_eq!(mock.commands(), vec![Unmap]);
+    assert_eq!(mock.stats(), vec![Stat::Window(WindowStat::Dimension)]);
+
+    // The script is exhausted and terminate closed the session
+    assert_eq!(connect.poll(&token), Err(ren::Error::Closed));
+}
+
+#[test]
+fn sessions()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let a = connect.begin();
+    let b = connect.begin();
+    let mock_a = Mock::session(&connect, &a).unwrap();
+    let mock_b = Mock::session(&connect, &b).unwrap();
+    assert_ne!(mock_a, mock_b);
+
+    connect.requests(&a, &[Title("a".into()), Map]);
+    connect.request(&b, Dimension((10, 10))).unwrap();
+
+    assert_eq!(mock_a.commands(), vec![Title("a".into()), Map]);
+    assert_eq!(mock_b.commands(), vec![Dimension((10, 10))]);
+
+    mock_a.clear();
+    assert!(mock_a.commands().is_empty());
+}
+
+#[test]
+fn any()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let a = connect.begin();
+    let b = connect.begin();
+
+    Mock::session(&connect, &b).unwrap().script(vec![Event::Terminate]);
+    assert_eq!(connect.poll_any(), Ok((b, Event::Terminate)));
+    assert_eq!(connect.poll_any(), Err(ren::Error::NoEvent));
+
+    // Blocks until the script is extended from another thread
+    let mock = Mock::session(&connect, &a).unwrap();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        mock.script(vec![Event::Unknown(None)]);
+    });
+    assert_eq!(connect.wait_any(), Ok((a, Event::Unknown(None))));
+    handle.join().unwrap();
+
+    // Nothing is left to wait for
+    connect.end(&a).unwrap();
+    assert_eq!(connect.wait_any(), Err(ren::Error::NoEvent));
+}
+
+#[test]
+fn drain()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    assert_eq!(connect.fd(), None);
+    assert!(connect.drain().is_empty());
+
+    let mock = Mock::session(&connect, &token).unwrap();
+    mock.script(vec![Event::Unknown(Some(1)), Event::Terminate, Event::Unknown(Some(2))]);
+
+    assert_eq!(connect.drain(), vec![(token, Event::Unknown(Some(1))), (token, Event::Terminate)]);
+    assert_eq!(connect.drain(), vec![(token, Event::Unknown(Some(2)))]);
+}
+
+#[test]
+fn dispatch()
+{
+    use ren::{Message, MessageQueue, Error, data::WindowData};
+
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+
+    let mut queue = MessageQueue::new();
+    queue.enqueue(Message::request(Title("batch".into())));
+    queue.enqueue(Message::request(Update));
+    queue.enqueue(Message::request(Map));
+    queue.enqueue(Message::request(Update));
+    queue.enqueue(Message::request(WindowStat::Title));
+    let batch = connect.batch(&token, queue).unwrap();
+
+    let responses = connect.dispatch(&token, &batch).unwrap();
+    assert_eq!(responses.len(), 5);
+    assert_eq!(responses[4], Ok(Message::response(WindowData::Title("batch".into()))));
+    assert_eq!(mock.commands(), vec![Title("batch".into()), Map]);
+    assert_eq!(mock.updates(), 1);
+
+    // A failed message does not stop the rest of the batch
+    mock.clear();
+    let mut queue = MessageQueue::new();
+    queue.enqueue(Message::request(WindowStat::Title));
+    queue.enqueue(Message::empty());
+    queue.enqueue(Message::request(Unmap));
+    let batch = connect.batch(&token, queue).unwrap();
+
+    let responses = connect.dispatch(&token, &batch).unwrap();
+    assert_eq!(responses[0], Ok(Message::response(WindowData::Title("batch".into()))));
+    assert_eq!(responses[1], Err(Error::Type));
+    assert_eq!(responses[2], Ok(Message::empty()));
+    assert_eq!(mock.commands(), vec![Unmap]);
+
+    // An invalid message cancels a validated queue
+    mock.clear();
+    let mut queue = MessageQueue::new();
+    queue.validate(true);
+    queue.enqueue(Message::request(Map));
+    queue.enqueue(Message::request(Dimension((0, 0))));
+    let batch = connect.batch(&token, queue).unwrap();
+
+    assert!(matches!(connect.dispatch(&token, &batch), Err(Error::Protocol { code: 2, .. })));
+    assert_eq!(mock.commands(), vec![]);
+    assert_eq!(mock.updates(), 0);
+    // The batch can still be dispatched again
+    assert!(matches!(connect.dispatch(&token, &batch), Err(Error::Protocol { .. })));
+}
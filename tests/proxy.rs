@@ -0,0 +1,112 @@
+extern crate ren;
+
+use std::{thread, time::{Duration, Instant}};
+use ren::{SystemType, Event, Error, Mock};
+
+#[test]
+fn post()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    let proxy = connect.proxy(&token).unwrap();
+
+    let handles: Vec<_> = (0..2).map(|i| {
+        let proxy = proxy.clone();
+        thread::spawn(move || proxy.post(format!("{}", i)).unwrap())
+    }).collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let mut events = vec![
+        connect.wait(&token).unwrap(),
+        connect.wait(&token).unwrap()
+    ];
+    events.sort_by_key(|e| format!("{:?}", e));
+    assert_eq!(events, vec![Event::User("0".into()), Event::User("1".into())]);
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+}
+
+#[test]
+fn wakeup()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    let proxy = connect.proxy(&token).unwrap();
+
+    // Block on a timer far in the future
+    connect.timer(&token, Duration::from_secs(10)).unwrap();
+
+    let start = Instant::now();
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        proxy.post("wake").unwrap();
+    });
+
+    assert_eq!(connect.wait(&token), Ok(Event::User("wake".into())));
+    assert!(start.elapsed() < Duration::from_secs(10));
+    handle.join().unwrap();
+}
+
+#[test]
+fn block()
+{
+    for ty in [SystemType::Headless, SystemType::Mock].iter() {
+        let mut connect = ren::Connection::open_with(*ty).unwrap();
+        let token = connect.begin();
+        let proxy = connect.proxy(&token).unwrap();
+
+        // No timer, only the post can end the wait
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            proxy.post("wake").unwrap();
+        });
+
+        assert_eq!(connect.wait(&token), Ok(Event::User("wake".into())));
+        handle.join().unwrap();
+    }
+}
+
+#[test]
+fn mock()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+    let proxy = connect.proxy(&token).unwrap();
+
+    mock.script(vec![Event::Terminate]);
+    proxy.post("after").unwrap();
+
+    assert_eq!(connect.wait(&token), Ok(Event::Terminate));
+    assert_eq!(connect.wait(&token), Ok(Event::User("after".into())));
+}
+
+#[test]
+fn ended()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    let proxy = connect.proxy(&token).unwrap();
+
+    connect.end(&token).unwrap();
+    assert_eq!(proxy.post("gone"), Err(Error::Token));
+    assert_eq!(connect.proxy(&token).err(), Some(Error::Token));
+}
+
+#[test]
+fn any()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let _ = connect.begin();
+    let token = connect.begin();
+    let proxy = connect.proxy(&token).unwrap();
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        proxy.post("any").unwrap();
+    });
+
+    assert_eq!(connect.wait_any(), Ok((token, Event::User("any".into()))));
+    handle.join().unwrap();
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "record")]
+
+extern crate ren;
+
+use std::{thread, path::PathBuf, time::{Duration, Instant}};
+use ren::{SystemType, Event, Error, WindowCommand::*};
+
+fn path(name: &str) -> PathBuf
+{
+    std::env::temp_dir().join(format!("ren-{}-{}.events", name, std::process::id()))
+}
+
+/// Record the events of a headless session, with a delay before the last event
+fn record(path: &PathBuf, delay: Duration) -> Vec<Event>
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    let proxy = connect.proxy(&session).unwrap();
+    connect.record(&session, path).unwrap();
+
+    connect.requests(&session, &[Dimension((64, 48)), Map]);
+    let mut events = vec![connect.wait(&session).unwrap(), connect.wait(&session).unwrap()];
+    thread::sleep(delay);
+    proxy.post("last").unwrap();
+    events.push(connect.wait(&session).unwrap());
+
+    connect.end_record(&session).unwrap();
+    // Not recorded
+    proxy.post("after").unwrap();
+    connect.wait(&session).unwrap();
+    events
+}
+
+#[test]
+fn replay()
+{
+    let path = path("replay");
+    let events = record(&path, Duration::from_millis(100));
+    assert_eq!(events.len(), 3);
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    connect.replay(&session, &path, 1.0).unwrap();
+
+    let start = Instant::now();
+    let replayed: Vec<_> = (0..3).map(|_| connect.wait(&session).unwrap()).collect();
+    assert_eq!(replayed, events);
+    assert!(start.elapsed() >= Duration::from_millis(100));
+
+    // The session is live again once the replay is over
+    assert_eq!(connect.poll(&session), Err(Error::NoEvent));
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn accelerated()
+{
+    let path = path("accelerated");
+    let events = record(&path, Duration::from_millis(500));
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    connect.replay(&session, &path, 10.0).unwrap();
+
+    // The delayed event is not due yet
+    assert_eq!(connect.wait(&session), Ok(events[0].clone()));
+    assert_eq!(connect.wait(&session), Ok(events[1].clone()));
+    assert_eq!(connect.poll(&session), Err(Error::NoEvent));
+
+    let start = Instant::now();
+    assert_eq!(connect.wait(&session), Ok(events[2].clone()));
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    assert!(connect.replay(&session, &path, 0.0).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn closed()
+{
+    let path = path("closed");
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    connect.record(&session, &path).unwrap();
+
+    // The session is closed before the event is delivered
+    connect.request(&session, Close).unwrap();
+    assert_eq!(connect.wait(&session), Ok(Event::Terminate));
+    connect.end_record(&session).unwrap();
+
+    let lines = std::fs::read_to_string(&path).unwrap().lines().count();
+    assert_eq!(lines, 1);
+    std::fs::remove_file(&path).unwrap();
+}
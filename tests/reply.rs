@@ -0,0 +1,65 @@
+extern crate ren;
+
+use ren::{SystemType, Event, Error, Message, Mock, Stat, stat::WindowStat, WindowCommand::*};
+
+#[test]
+fn reply()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    connect.request(&session, Dimension((320, 240))).unwrap();
+
+    let id = connect.request_async(&session, WindowStat::Dimension).unwrap();
+    let response = connect.reply(&session, &id).unwrap();
+    let expected = connect.request(&session, WindowStat::Dimension).unwrap();
+
+    assert_eq!(response.id(), Some(&id));
+    assert_eq!(response.body(), expected.body());
+    assert_eq!(expected.id(), None);
+
+    // Collected once
+    assert_eq!(connect.reply(&session, &id), Err(Error::Token));
+    while let Ok(event) = connect.poll(&session) {
+        assert!(!matches!(event, Event::Reply(_)));
+    }
+}
+
+#[test]
+fn events()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+
+    let ids: Vec<_> = [WindowStat::Position, WindowStat::Dimension, WindowStat::Mapped]
+        .iter()
+        .map(|stat| connect.request_async(&session, *stat).unwrap())
+        .collect();
+    // Taken out of order by its handle
+    connect.wait_reply(&session, &ids[1]).unwrap();
+
+    for id in [ids[0], ids[2]].iter() {
+        match connect.wait(&session) {
+            Ok(Event::Reply(response)) => assert_eq!(response.id(), Some(id)),
+            event => panic!("unexpected {:?}", event)
+        }
+    }
+    assert_eq!(connect.poll(&session), Err(Error::NoEvent));
+}
+
+#[test]
+fn invalid()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let session = connect.begin();
+    let mock = Mock::session(&connect, &session).unwrap();
+
+    assert_eq!(connect.request_async(&session, Map), Err(Error::Type));
+    assert_eq!(connect.send_async(&session, Message::empty()), Err(Error::Type));
+    assert_eq!(mock.stats(), vec![]);
+
+    connect.request_async(&session, WindowStat::Title).unwrap();
+    assert_eq!(mock.stats(), vec![Stat::Window(WindowStat::Title)]);
+
+    connect.end(&session).unwrap();
+    assert_eq!(connect.request_async(&session, WindowStat::Title), Err(Error::Token));
+}
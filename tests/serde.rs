@@ -0,0 +1,90 @@
+#![cfg(feature = "serde")]
+
+extern crate ren;
+
+use ren::{
+    SystemType, Message, Event, DisplayEvent, MouseEvent, Body, Data,
+    event::input::{MouseData, MouseInput},
+    data::{WindowData, XcbData}, stat::WindowStat,
+    WindowCommand::*
+};
+
+fn round_trip(message: Message) -> Message
+{
+    let json = serde_json::to_string(&message).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn message()
+{
+    let messages = vec![
+        Message::request(Title("serde".into())),
+        Message::request(WindowStat::Dimension),
+        Message::response(WindowData::Parent(Some(7))),
+        Message::response(XcbData::Window(0x400001)),
+        Message::response(Event::Display(DisplayEvent::Resize((320, 240)))),
+        Message::response(Event::from(MouseEvent::Press(MouseData::new(MouseInput::Left, (4, 2))))),
+        Message::response(Event::User("user".into())),
+        Message::empty()
+    ];
+    for message in messages {
+        assert_eq!(round_trip(message.clone()), message);
+    }
+}
+
+#[test]
+#[cfg(target_family = "unix")]
+fn key()
+{
+    use ren::{KeyEvent, event::input::{KeyInput, KeyMap, keyboard::Modifier}};
+
+    let input = KeyInput::from_map(KeyMap::Escape, Some(&[Modifier::Ctrl, Modifier::Shift])).unwrap();
+    let event = Message::response(Event::from(KeyEvent::Press(input)));
+    let copy = round_trip(event.clone());
+    assert_eq!(copy, event);
+
+    // The key map survives the round trip
+    match copy.body() {
+        Body::Event(Event::Input(ren::InputEvent::Key(KeyEvent::Press(input)))) => {
+            assert_eq!(input.map(), Some(KeyMap::Escape));
+        },
+        body => panic!("unexpected body {:?}", body)
+    }
+}
+
+#[test]
+fn modifiers()
+{
+    use ren::event::input::{KeyInput, keyboard::Modifier};
+
+    // The modifiers are restored on every platform
+    let input: KeyInput = serde_json::from_str(r#"{"code":9,"mods":["Shift","Ctrl"]}"#).unwrap();
+    assert_eq!(input.code(), 9);
+    assert_eq!(input.modifiers(), Some(&[Modifier::Ctrl, Modifier::Shift][..]));
+    assert_eq!(serde_json::from_str::<KeyInput>(&serde_json::to_string(&input).unwrap()).unwrap(), input);
+}
+
+#[test]
+fn timer()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let session = connect.begin();
+    let timer = connect.timer(&session, std::time::Duration::from_millis(0)).unwrap();
+
+    let event = Message::response(connect.wait(&session).unwrap());
+    assert_eq!(event.body(), &Body::Event(Event::Timer(timer)));
+    assert_eq!(round_trip(event.clone()), event);
+}
+
+#[test]
+fn skipped()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let session = connect.begin();
+
+    // Handles to the system are not serialized
+    let handle = connect.request(&session, ren::stat::MockStat::Handle).unwrap();
+    assert!(matches!(handle.body(), Body::Data(Data::Mock(_))));
+    assert!(serde_json::to_string(&handle).is_err());
+}
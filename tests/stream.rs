@@ -0,0 +1,126 @@
+#![cfg(any(feature = "async-rt", feature = "tokio-rt"))]
+
+extern crate ren;
+
+use std::{pin::Pin, task::{Context, Poll, Waker}};
+use ren::{Mock, SystemType, Event, futures_core::Stream};
+
+#[test]
+fn poll()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+    mock.script(vec![Event::Terminate]);
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut events = connect.events(&token).unwrap();
+    assert_eq!(Pin::new(&mut events).poll_next(&mut cx), Poll::Ready(Some(Event::Terminate)));
+    assert_eq!(Pin::new(&mut events).poll_next(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+#[cfg(feature = "async-rt")]
+fn events()
+{
+    use ren::async_std::{task, stream::StreamExt};
+
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+    mock.script(vec![Event::Unknown(Some(1)), Event::Unknown(Some(2)), Event::Terminate]);
+
+    task::block_on(async {
+        assert_eq!(connect.event(&token).await, Ok(Event::Unknown(Some(1))));
+
+        let events: Vec<_> = connect.events(&token).unwrap().collect().await;
+        assert_eq!(events, vec![Event::Unknown(Some(2)), Event::Terminate]);
+
+        // The terminate closed the session
+        assert_eq!(connect.event(&token).await, Err(ren::Error::Closed));
+    });
+}
+
+#[test]
+#[cfg(feature = "async-rt")]
+fn timer()
+{
+    use std::time::Duration;
+    use ren::async_std::{task, stream::StreamExt};
+
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+    let timer = connect.timer(&token, Duration::from_millis(20)).unwrap();
+
+    task::block_on(async {
+        // Woken by the deadline rather than ending the stream
+        let mut events = connect.events(&token).unwrap();
+        assert_eq!(events.next().await, Some(Event::Timer(timer)));
+        assert_eq!(events.next().await, None);
+    });
+}
+
+#[test]
+#[cfg(feature = "async-rt")]
+fn closed()
+{
+    use ren::async_std::{task, stream::StreamExt};
+
+    let mut connect = ren::Connection::open_with(SystemType::Mock).unwrap();
+    let token = connect.begin();
+    let mock = Mock::session(&connect, &token).unwrap();
+    mock.script(vec![Event::Unknown(None), Event::Terminate]);
+
+    task::block_on(async {
+        // Streams of a session can be used side by side
+        let mut first = connect.events(&token).unwrap();
+        let mut second = connect.events(&token).unwrap();
+        assert_eq!(first.next().await, Some(Event::Unknown(None)));
+        assert_eq!(second.next().await, Some(Event::Terminate));
+        assert_eq!(first.next().await, None);
+
+        // The error is not hidden behind `NoEvent`
+        assert_eq!(connect.event(&token).await, Err(ren::Error::Closed));
+    });
+}
+
+#[test]
+#[cfg(feature = "async-rt")]
+#[ignore = "requires an X server"]
+fn sessions()
+{
+    use std::{sync::{Arc, atomic::{AtomicBool, Ordering}}, task::Wake, thread, time::Duration};
+    use ren::{async_std::{task, stream::StreamExt}, WindowCommand::*};
+
+    /// Records that the stream was woken
+    struct Woken(AtomicBool);
+
+    impl Wake for Woken {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let mut connect = ren::Connection::open_with(SystemType::Xcb).unwrap();
+    let first = connect.begin();
+    let second = connect.begin();
+
+    // Wait on the second session before it has events
+    let mut events = connect.events(&second).unwrap();
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = Waker::from(Arc::clone(&woken));
+    let mut cx = Context::from_waker(&waker);
+    assert_eq!(Pin::new(&mut events).poll_next(&mut cx), Poll::Pending);
+
+    connect.request(&first, Map).unwrap();
+    connect.request(&second, Map).unwrap();
+    // Both windows are exposed before the next read
+    thread::sleep(Duration::from_millis(200));
+
+    // Reading for the first session routes the events of the second
+    task::block_on(async {
+        assert!(connect.events(&first).unwrap().next().await.is_some());
+    });
+    assert!(woken.0.load(Ordering::SeqCst));
+    assert!(matches!(Pin::new(&mut events).poll_next(&mut cx), Poll::Ready(Some(_))));
+}
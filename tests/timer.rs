@@ -0,0 +1,71 @@
+extern crate ren;
+
+use std::time::{Duration, Instant};
+use ren::{SystemType, Event, Error, WindowCommand::*};
+
+#[test]
+fn timeout()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    let start = Instant::now();
+    let timer = connect.timer(&token, Duration::from_millis(50)).unwrap();
+
+    // Not expired yet
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+
+    assert_eq!(connect.wait(&token), Ok(Event::Timer(timer)));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    // A one-shot timer only expires once
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+    assert_eq!(connect.cancel(&token, &timer), Err(Error::Token));
+}
+
+#[test]
+fn interval()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    let timer = connect.interval(&token, Duration::from_millis(10)).unwrap();
+    for _ in 0..3 {
+        assert_eq!(connect.wait(&token), Ok(Event::Timer(timer)));
+    }
+
+    connect.cancel(&token, &timer).unwrap();
+    assert_eq!(connect.poll(&token), Err(Error::NoEvent));
+}
+
+#[test]
+fn events()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let token = connect.begin();
+
+    let late = connect.timer(&token, Duration::from_millis(40)).unwrap();
+    let early = connect.timer(&token, Duration::from_millis(20)).unwrap();
+    connect.request(&token, Dimension((20, 20))).unwrap();
+
+    // Window events do not wait for timers
+    assert_eq!(connect.wait(&token), Ok(ren::DisplayEvent::Resize((20, 20)).into()));
+    assert_eq!(connect.wait(&token), Ok(Event::Timer(early)));
+    assert_eq!(connect.wait(&token), Ok(Event::Timer(late)));
+}
+
+#[test]
+fn any()
+{
+    let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+    let idle = connect.begin();
+    let token = connect.begin();
+
+    let start = Instant::now();
+    let timer = connect.timer(&token, Duration::from_millis(30)).unwrap();
+
+    // The wait ends at the deadline of another session
+    assert_eq!(connect.wait_any(), Ok((token, Event::Timer(timer))));
+    assert!(start.elapsed() >= Duration::from_millis(30));
+    assert_eq!(connect.poll(&idle), Err(Error::NoEvent));
+}
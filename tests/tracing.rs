@@ -0,0 +1,79 @@
+#![cfg(feature = "tracing")]
+
+extern crate ren;
+
+use std::{fmt, sync::{Arc, Mutex, atomic::{AtomicU64, Ordering}}};
+use tracing::{span, field::{Field, Visit}, Event, Metadata, Subscriber};
+use ren::{SystemType, Message, MessageQueue, stat::WindowStat, WindowCommand::*};
+
+/// Collects the name and fields of each span and event
+#[derive(Default)]
+struct Collect {
+    ids: AtomicU64,
+    lines: Arc<Mutex<Vec<String>>>
+}
+
+struct Line(String);
+
+impl Visit for Line {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0 += &format!(" {}={:?}", field.name(), value);
+    }
+}
+
+impl Subscriber for Collect {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let mut line = Line(span.metadata().name().to_string());
+        span.record(&mut line);
+        self.lines.lock().unwrap().push(line.0);
+        span::Id::from_u64(self.ids.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut line = Line(String::from("event"));
+        event.record(&mut line);
+        self.lines.lock().unwrap().push(line.0);
+    }
+
+    fn enter(&self, _: &span::Id) {}
+
+    fn exit(&self, _: &span::Id) {}
+}
+
+#[test]
+fn spans()
+{
+    let collect = Collect::default();
+    let lines = collect.lines.clone();
+
+    tracing::subscriber::with_default(collect, || {
+        let mut connect = ren::Connection::open_with(SystemType::Headless).unwrap();
+        let session = connect.begin();
+        connect.request(&session, WindowStat::Dimension).unwrap();
+
+        let mut queue = MessageQueue::new();
+        queue.enqueue(Message::request(Map));
+        let batch = connect.batch(&session, queue).unwrap();
+        connect.dispatch(&session, &batch).unwrap();
+        connect.end(&session).unwrap();
+    });
+
+    let lines = lines.lock().unwrap();
+    let find = |prefix: &str| lines.iter().position(|l| l.starts_with(prefix))
+        .unwrap_or_else(|| panic!("no `{}` in {:?}", prefix, lines));
+
+    assert!(find("open_with ty=Headless") < find("begin_with"));
+    assert!(find("begin_with") < find("event message=session begin"));
+    assert!(find("handle ty=Request body=\"stat\"") < find("dispatch"));
+    assert!(lines.iter().any(|l| l == "handle ty=Request body=\"command\""));
+    assert!(find("dispatch") < find("event message=batch dispatched responses=1 failed=0 elapsed="));
+    assert!(find("event message=batch dispatched") < find("end"));
+}